@@ -0,0 +1,91 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use interp::parser::{Parser, ParserOptions};
+use interp::{Lexer, TokenKind};
+
+/// A program of `copies` repetitions of a snippet exercising most tokens.
+fn generate_program(copies: usize) -> String {
+    let snippet = r#"
+let five = 5;
+let ten = 10;
+let result = five * (ten + 2) / 3 - -1;
+if (result < ten) { return true; } else { return false; }
+let flag = !(five == ten) != false;
+try { result } catch (err) { err }
+"#;
+    let mut source = String::with_capacity(snippet.len() * copies);
+    for idx in 0..copies {
+        source.push_str(&snippet.replace("result", &format!("result_{}", idx)));
+    }
+    source
+}
+
+/// An expression nested `depth` parentheses deep.
+fn generate_nested(depth: usize) -> String {
+    let mut source = String::new();
+    for _ in 0..depth {
+        source.push_str("(1 + ");
+    }
+    source.push('1');
+    for _ in 0..depth {
+        source.push(')');
+    }
+    source
+}
+
+fn lex_all(source: &str) -> usize {
+    let mut lexer = Lexer::new(source);
+    let mut count = 0;
+    while lexer.next_token() != TokenKind::EOF {
+        count += 1;
+    }
+    count
+}
+
+fn bench_lexer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lexer");
+    for copies in [10, 1000] {
+        let source = generate_program(copies);
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(copies), &source, |b, source| {
+            b.iter(|| lex_all(black_box(source)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_parser(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parser");
+    for copies in [10, 1000] {
+        let source = generate_program(copies);
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(copies), &source, |b, source| {
+            b.iter(|| Parser::from_input(black_box(source)).parse_program())
+        });
+    }
+    group.finish();
+}
+
+fn bench_parser_nested(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parser_nested");
+    // Each level is a group and the right operand of a `+`.
+    let options = ParserOptions {
+        max_nesting_depth: 512,
+        ..ParserOptions::default()
+    };
+    let parse = |source: &str| {
+        Parser::from_input(source)
+            .with_options(options)
+            .parse_program()
+    };
+    for depth in [10, 200] {
+        let source = generate_nested(depth);
+        assert!(parse(&source).is_ok());
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &source, |b, source| {
+            b.iter(|| parse(black_box(source)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_lexer, bench_parser, bench_parser_nested);
+criterion_main!(benches);
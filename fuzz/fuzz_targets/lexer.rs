@@ -0,0 +1,21 @@
+#![no_main]
+use interp::{Lexer, TokenKind};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let mut lexer = Lexer::new(input);
+    let mut previous_end = 0;
+    loop {
+        let tok = lexer.next_token();
+        let span = tok.span;
+        // Tokens must make progress through the input, in order, and land on
+        // character boundaries.
+        assert!(span.start >= previous_end && span.end >= span.start);
+        assert!(input.is_char_boundary(span.start) && input.is_char_boundary(span.end));
+        previous_end = span.end;
+        if tok == TokenKind::EOF {
+            break;
+        }
+        assert!(!span.is_empty(), "empty span for {:?}", tok);
+    }
+});
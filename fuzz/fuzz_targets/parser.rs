@@ -0,0 +1,13 @@
+#![no_main]
+use interp::parser::Parser;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let mut parser = Parser::from_input(input);
+    // Printing must not panic or overflow on anything the parser produces,
+    // including what it recovers from a failed parse.
+    let program = parser
+        .parse_program()
+        .unwrap_or_else(|failure| failure.program);
+    let _ = program.to_string();
+});
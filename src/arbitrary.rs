@@ -0,0 +1,277 @@
+//! `proptest` generators for the AST, used to check that printing a program
+//! and parsing the result gives back the same tree.
+//!
+//! The generators only build trees the parser can produce: integer literals
+//! are never negative, identifiers are never keywords, strings never contain
+//! a quote, interpolated strings never hold empty text, and every statement and block carries the token the parser would
+//! have recorded for it.
+
+use crate::ast::{BlockStatement, Expression, Identifier, Pattern, Program, Statement, StringPart};
+use crate::token::{Token, TokenKind};
+use proptest::prelude::*;
+
+impl Arbitrary for Identifier {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        "[a-zé変][a-zA-Z0-9_é変]{0,7}"
+            .prop_filter("keywords are not identifiers", |name| {
+                Token::lookup_ident(name) == TokenKind::Ident
+            })
+            .prop_map(|name| Identifier::new(&name))
+            .boxed()
+    }
+}
+
+impl Arbitrary for Expression {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        let leaf = prop_oneof![
+            any::<Identifier>().prop_map(Expression::Identifier),
+            (0..=i64::MAX).prop_map(Expression::IntegerLiteral),
+            "[a-zA-Z0-9 ${}]{0,8}".prop_map(Expression::StringLiteral),
+            any::<bool>().prop_map(Expression::Boolean),
+        ];
+        leaf.prop_recursive(4, 32, 4, |inner| {
+            let prefix_operator = prop_oneof![
+                Just(Token::from(TokenKind::Bang)),
+                Just(Token::from(TokenKind::Minus)),
+                Just(Token::from(TokenKind::Plus)),
+                Just(Token::from(TokenKind::Tilde))
+            ];
+            let infix_operator = prop_oneof![
+                Just(Token::from(TokenKind::Plus)),
+                Just(Token::from(TokenKind::Minus)),
+                Just(Token::from(TokenKind::Asterisk)),
+                Just(Token::from(TokenKind::Slash)),
+                Just(Token::from(TokenKind::Power)),
+                Just(Token::from(TokenKind::Lt)),
+                Just(Token::from(TokenKind::Gt)),
+                Just(Token::from(TokenKind::ShiftLeft)),
+                Just(Token::from(TokenKind::ShiftRight)),
+                Just(Token::from(TokenKind::Ampersand)),
+                Just(Token::from(TokenKind::Bar)),
+                Just(Token::from(TokenKind::Caret)),
+                Just(Token::from(TokenKind::Eq)),
+                Just(Token::from(TokenKind::NotEq)),
+            ];
+            let block = block_statement(inner.clone());
+            prop_oneof![
+                (prefix_operator, inner.clone())
+                    .prop_map(|(operator, right)| Expression::new_prefix(operator, right)),
+                (inner.clone(), infix_operator, inner.clone()).prop_map(
+                    |(left, operator, right)| Expression::new_infix(left, operator, right)
+                ),
+                (inner.clone(), inner.clone()).prop_map(|(left, index)| Expression::Index {
+                    left: Box::new(left),
+                    index: Box::new(index),
+                }),
+                (
+                    inner.clone(),
+                    proptest::option::of(inner.clone()),
+                    proptest::option::of(inner.clone())
+                )
+                    .prop_map(|(left, start, end)| Expression::Slice {
+                        left: Box::new(left),
+                        start: start.map(Box::new),
+                        end: end.map(Box::new),
+                    }),
+                proptest::collection::vec(inner.clone(), 0..3).prop_map(Expression::ArrayLiteral),
+                (inner.clone(), inner.clone(), any::<bool>()).prop_map(
+                    |(start, end, inclusive)| Expression::Range {
+                        start: Box::new(start),
+                        end: Box::new(end),
+                        inclusive,
+                    }
+                ),
+                (
+                    inner.clone(),
+                    proptest::collection::vec(inner.clone(), 0..3)
+                )
+                    .prop_map(|(function, arguments)| Expression::Call {
+                        function: Box::new(function),
+                        arguments,
+                    }),
+                (
+                    proptest::collection::vec((text(), inner.clone()), 1..3),
+                    text()
+                )
+                    .prop_map(|(pieces, last)| {
+                        let mut parts = vec![];
+                        for (text, expr) in pieces {
+                            if !text.is_empty() {
+                                parts.push(StringPart::Text(text));
+                            }
+                            parts.push(StringPart::Expr(expr));
+                        }
+                        if !last.is_empty() {
+                            parts.push(StringPart::Text(last));
+                        }
+                        Expression::Interpolated(parts)
+                    }),
+                (inner, block.clone(), proptest::option::of(block.clone())).prop_map(
+                    |(condition, consequence, alternative)| Expression::If {
+                        condition: Box::new(condition),
+                        consequence,
+                        alternative,
+                    }
+                ),
+                (block.clone(), any::<Identifier>(), block.clone()).prop_map(
+                    |(body, binding, handler)| Expression::Try {
+                        body,
+                        binding,
+                        handler,
+                    }
+                ),
+                (
+                    proptest::collection::vec(any::<Identifier>(), 0..3),
+                    block,
+                    doc()
+                )
+                    .prop_map(|(parameters, body, doc)| Expression::Function {
+                        parameters,
+                        body,
+                        doc,
+                    }),
+            ]
+        })
+        .boxed()
+    }
+}
+
+impl Arbitrary for Statement {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        statement(any::<Expression>())
+    }
+}
+
+impl Arbitrary for Program {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        proptest::collection::vec(any::<Statement>(), 0..5)
+            .prop_map(|statements| Program { statements })
+            .boxed()
+    }
+}
+
+fn statement(expr: BoxedStrategy<Expression>) -> BoxedStrategy<Statement> {
+    prop_oneof![
+        (binding_token(), any::<Identifier>(), expr.clone(), doc()).prop_map(
+            |(token, name, value, doc)| Statement::Let {
+                token,
+                name,
+                value,
+                doc,
+            }
+        ),
+        (binding_token(), destructuring_pattern(), expr.clone()).prop_map(
+            |(token, pattern, value)| Statement::Destructure {
+                token,
+                pattern,
+                value,
+            }
+        ),
+        proptest::option::of(expr.clone()).prop_map(|expr| Statement::Return {
+            token: Token::from(TokenKind::Return),
+            expr: expr.unwrap_or(Expression::Nothing),
+        }),
+        expr.prop_map(|expr| Statement::Expression {
+            token: first_token(&expr),
+            expr,
+        }),
+    ]
+    .boxed()
+}
+
+fn block_statement(expr: BoxedStrategy<Expression>) -> BoxedStrategy<BlockStatement> {
+    proptest::collection::vec(statement(expr), 0..3)
+        .prop_map(|statements| BlockStatement {
+            token: Token::from(TokenKind::LBrace),
+            statements,
+        })
+        .boxed()
+}
+
+fn binding_token() -> impl Strategy<Value = Token> {
+    prop_oneof![
+        Just(Token::from(TokenKind::Let)),
+        Just(Token::from(TokenKind::Const))
+    ]
+}
+
+/// An array or hash pattern, as a plain name would parse as `Statement::Let`.
+fn destructuring_pattern() -> impl Strategy<Value = Pattern> {
+    let names = || proptest::collection::vec(any::<Identifier>(), 0..3);
+    let leaf = prop_oneof![
+        any::<Identifier>().prop_map(Pattern::Identifier),
+        names().prop_map(Pattern::Hash),
+    ];
+    let pattern = leaf.prop_recursive(2, 8, 3, |inner| {
+        proptest::collection::vec(inner, 0..3).prop_map(Pattern::Array)
+    });
+    prop_oneof![
+        proptest::collection::vec(pattern, 0..3).prop_map(Pattern::Array),
+        names().prop_map(Pattern::Hash),
+    ]
+}
+
+/// String literal text, free of quotes and `${`.
+fn text() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9 ]{0,8}"
+}
+
+/// An occasional doc comment of one or two lines.
+fn doc() -> impl Strategy<Value = Option<String>> {
+    proptest::option::weighted(
+        0.2,
+        proptest::collection::vec("[a-zA-Z ]{0,8}", 1..3).prop_map(|lines| lines.join("\n")),
+    )
+}
+
+/// The token an expression statement starts with once printed.
+fn first_token(expr: &Expression) -> Token {
+    match expr {
+        Expression::Identifier(name) => Token::make_ident(&name.0),
+        Expression::IntegerLiteral(value) => Token::make_int(&value.to_string()),
+        Expression::StringLiteral(value) => Token::new(TokenKind::String, value.clone()),
+        Expression::Boolean(true) => Token::from(TokenKind::True),
+        Expression::Boolean(false) => Token::from(TokenKind::False),
+        Expression::If { .. } => Token::from(TokenKind::If),
+        Expression::Try { .. } => Token::from(TokenKind::Try),
+        Expression::Function { .. } => Token::from(TokenKind::Function),
+        Expression::ArrayLiteral(_) => Token::from(TokenKind::LBracket),
+        Expression::Interpolated(parts) => match parts.first() {
+            Some(StringPart::Text(text)) => Token::new(TokenKind::InterpolationStart, text.clone()),
+            _ => Token::new(TokenKind::InterpolationStart, String::new()),
+        },
+        Expression::Call { function, .. } => first_token(function),
+        _ => Token::from(TokenKind::LParen),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::Parser;
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn test_display_round_trips(program in any::<Program>()) {
+            let source = program.to_string();
+            let mut parser = Parser::from_input(&source);
+            let parsed = parser.parse_program().unwrap();
+            prop_assert!(parser.errors().is_empty(), "{:?} in {}", parser.errors(), source);
+            prop_assert_eq!(parsed.statements, program.statements, "in {}", source);
+        }
+    }
+}
@@ -1,12 +1,37 @@
-use crate::token::Token;
+use crate::parser::{Parser, ParserError, Precedence};
+use crate::span::Span;
+use crate::token::{Token, TokenKind};
+use crate::visit::{AstNode, Walk};
 use std::fmt::{self, Display};
+use std::mem;
+use std::slice;
+use std::vec;
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Identifier(pub String);
+/// A name, and where it was read from. As with `Token`, the span is left
+/// out of comparisons and debug output.
+#[derive(Clone)]
+pub struct Identifier(pub String, pub Span);
 
 impl Identifier {
     pub fn new(ident: &str) -> Self {
-        Identifier(ident.to_owned())
+        Identifier(ident.to_owned(), Span::default())
+    }
+    /// The same name, read from `span`.
+    pub fn at(mut self, span: Span) -> Self {
+        self.1 = span;
+        self
+    }
+}
+
+impl PartialEq for Identifier {
+    fn eq(&self, other: &Identifier) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl fmt::Debug for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Identifier").field(&self.0).finish()
     }
 }
 impl Display for Identifier {
@@ -22,10 +47,16 @@ impl From<&str> for Identifier {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Expression {
     Identifier(Identifier),
     IntegerLiteral(i64),
+    StringLiteral(String),
+    /// A string with `${expr}` interpolations. The parser never produces
+    /// empty text parts, nor one without any expressions.
+    Interpolated(Vec<StringPart>),
     Boolean(bool),
+    ArrayLiteral(Vec<Expression>),
     Prefix {
         operator: Token,
         right: Box<Expression>,
@@ -40,9 +71,49 @@ pub enum Expression {
         consequence: BlockStatement,
         alternative: Option<BlockStatement>,
     },
+    Try {
+        body: BlockStatement,
+        binding: Identifier,
+        handler: BlockStatement,
+    },
+    Function {
+        parameters: Vec<Identifier>,
+        body: BlockStatement,
+        /// Text of the `///` comments before the `fn`, if any.
+        doc: Option<String>,
+    },
+    /// `start..end`, or `start..=end` if `inclusive`.
+    Range {
+        start: Box<Expression>,
+        end: Box<Expression>,
+        inclusive: bool,
+    },
+    /// `function(arguments...)`.
+    Call {
+        function: Box<Expression>,
+        arguments: Vec<Expression>,
+    },
+    /// `left[index]`.
+    Index {
+        left: Box<Expression>,
+        index: Box<Expression>,
+    },
+    /// `left[start:end]`, where either bound may be left out.
+    Slice {
+        left: Box<Expression>,
+        start: Option<Box<Expression>>,
+        end: Option<Box<Expression>>,
+    },
     Nothing,
 }
 
+/// A piece of an interpolated string.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Text(String),
+    Expr(Expression),
+}
+
 impl From<bool> for Expression {
     fn from(b: bool) -> Self {
         Expression::Boolean(b)
@@ -81,42 +152,148 @@ impl Expression {
     }
 }
 
-impl Display for Expression {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Expression::Identifier(name) => write!(f, "{}", name),
-            Expression::IntegerLiteral(value) => write!(f, "{}", value),
-            Expression::Boolean(value) => write!(f, "{}", value),
-            Expression::Prefix { operator, right } => {
-                write!(f, "({}{})", operator.literal(), right)
+/// Dropping a deeply nested expression would otherwise recurse once per
+/// level, so children are moved onto a heap-allocated stack and dropped one
+/// at a time. Statements only nest through `Expression::If`, so draining the
+/// expressions out of its blocks keeps `Statement` drops shallow as well.
+impl Drop for Expression {
+    fn drop(&mut self) {
+        let mut stack = vec![];
+        take_children(self, &mut stack);
+        while let Some(mut expr) = stack.pop() {
+            take_children(&mut expr, &mut stack);
+        }
+    }
+}
+
+/// Move the direct child expressions of `expr` onto `stack`.
+fn take_children(expr: &mut Expression, stack: &mut Vec<Expression>) {
+    let take = |expr: &mut Expression| mem::replace(expr, Expression::Nothing);
+    match expr {
+        Expression::Prefix { right, .. } => stack.push(take(right)),
+        Expression::Infix { left, right, .. } => {
+            stack.push(take(left));
+            stack.push(take(right));
+        }
+        Expression::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            stack.push(take(condition));
+            for block in Some(consequence).into_iter().chain(alternative) {
+                take_block(block, stack);
             }
-            Expression::If {
-                condition,
-                consequence,
-                alternative,
-            } => {
-                write!(f, "if {} {}", condition, consequence)?;
-                if let Some(alternative) = alternative {
-                    write!(f, "else {}", alternative)?;
+        }
+        Expression::Try { body, handler, .. } => {
+            take_block(body, stack);
+            take_block(handler, stack);
+        }
+        Expression::Function { body, .. } => take_block(body, stack),
+        Expression::Index { left, index } => {
+            stack.push(take(left));
+            stack.push(take(index));
+        }
+        Expression::Slice { left, start, end } => {
+            stack.push(take(left));
+            for bound in start.iter_mut().chain(end) {
+                stack.push(take(bound));
+            }
+        }
+        Expression::ArrayLiteral(elements) => stack.append(elements),
+        Expression::Call {
+            function,
+            arguments,
+        } => {
+            stack.push(take(function));
+            stack.append(arguments);
+        }
+        Expression::Range { start, end, .. } => {
+            stack.push(take(start));
+            stack.push(take(end));
+        }
+        Expression::Interpolated(parts) => {
+            for part in parts {
+                if let StringPart::Expr(expr) = part {
+                    stack.push(take(expr));
                 }
-                Ok(())
             }
-            Expression::Infix {
-                left,
-                operator,
-                right,
-            } => write!(f, "({} {} {})", left, operator.literal(), right),
-            _ => Ok(()),
         }
+        Expression::Identifier(_)
+        | Expression::IntegerLiteral(_)
+        | Expression::StringLiteral(_)
+        | Expression::Boolean(_)
+        | Expression::Nothing => {}
+    }
+}
+
+/// Move the expressions held by a block's statements onto `stack`.
+fn take_block(block: &mut BlockStatement, stack: &mut Vec<Expression>) {
+    for stmt in &mut block.statements {
+        match stmt {
+            Statement::Let { value: expr, .. }
+            | Statement::Destructure { value: expr, .. }
+            | Statement::Return { expr, .. }
+            | Statement::Expression { expr, .. } => {
+                stack.push(mem::replace(expr, Expression::Nothing))
+            }
+        }
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        render(f, Node::Expr(self, 1), usize::MAX)
+    }
+}
+
+impl Expression {
+    /// Parse `source` as a single expression, optionally followed by a
+    /// semicolon, for callers with no use for statements.
+    pub fn parse(source: &str) -> Result<Expression, ParserError> {
+        let mut parser = Parser::from_input(source);
+        let expr = parser.parse_expression(Precedence::Lowest);
+        if parser.peek_token_is(TokenKind::Semicolon) {
+            parser.next_token();
+        }
+        // An illegal character or a lexer error is the cause of whatever
+        // went wrong after it.
+        parser.finish();
+        if let Some(err) = parser.errors().first() {
+            return Err(err.clone());
+        }
+        let expr = expr?;
+        if !parser.peek_token_is(TokenKind::EOF) {
+            return Err(parser.peek_error(TokenKind::EOF));
+        }
+        Ok(expr)
+    }
+
+    /// Display the expression, eliding anything nested more than
+    /// `max_depth` expressions deep as `...`.
+    pub fn depth_limited(&self, max_depth: usize) -> DepthLimited<'_> {
+        DepthLimited {
+            node: Node::Expr(self, 1),
+            max_depth,
+        }
+    }
+
+    /// Render as a Lisp-style s-expression, e.g. `(+ 1 (* 2 3))`.
+    pub fn to_sexpr(&self) -> String {
+        render_sexpr(Node::Expr(self, 1))
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Statement {
     Let {
+        /// `Let`, or `Const` for a constant binding.
         token: Token,
         name: Identifier,
         value: Expression,
+        /// Text of the `///` comments before the `let`, if any.
+        doc: Option<String>,
     },
     Return {
         token: Token,
@@ -126,29 +303,58 @@ pub enum Statement {
         token: Token,
         expr: Expression,
     },
+    /// A `let` binding an array or hash pattern, such as `let [a, b] = arr;`.
+    /// `let` with a plain name is always `Statement::Let`.
+    Destructure {
+        /// `Let` or `Const`, as for `Statement::Let`.
+        token: Token,
+        pattern: Pattern,
+        value: Expression,
+    },
 }
 
-impl Display for Statement {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Statement::Let { token, name, value } => {
-                write!(f, "{} {}", token.literal(), name)?;
-                match value {
-                    Expression::Nothing => (),
-                    _ => write!(f, " = {}", value)?,
-                }
-                write!(f, ";")
-            }
-            Statement::Return { token, expr } => {
-                write!(f, "{}", token.literal())?;
-                match expr {
-                    Expression::Nothing => (),
-                    _ => write!(f, " {}", expr)?,
-                }
-                write!(f, ";")
+/// The left-hand side of a destructuring `let`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Identifier(Identifier),
+    /// `[a, b, ...]`: bind an array's elements by position.
+    Array(Vec<Pattern>),
+    /// `{x, y, ...}`: bind the values a hash holds under each name.
+    Hash(Vec<Identifier>),
+}
+
+impl Pattern {
+    /// Every name the pattern binds, from left to right.
+    pub fn bindings(&self) -> Vec<&Identifier> {
+        let mut bindings = vec![];
+        let mut stack = vec![self];
+        while let Some(pattern) = stack.pop() {
+            match pattern {
+                Pattern::Identifier(name) => bindings.push(name),
+                Pattern::Array(elements) => stack.extend(elements.iter().rev()),
+                Pattern::Hash(names) => bindings.extend(names),
             }
-            Statement::Expression { expr, .. } => write!(f, "{}", expr),
         }
+        bindings
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        render(f, Node::Pattern(self), usize::MAX)
+    }
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        render(f, Node::Stmt(self, 1), usize::MAX)
+    }
+}
+
+impl Statement {
+    /// Render as a Lisp-style s-expression, e.g. `(let x (+ 1 2))`.
+    pub fn to_sexpr(&self) -> String {
+        render_sexpr(Node::Stmt(self, 1))
     }
 }
 
@@ -165,14 +371,45 @@ impl BlockStatement {
             statements: vec![],
         }
     }
+
+    /// Iterate over the block's own statements.
+    pub fn iter(&self) -> slice::Iter<'_, Statement> {
+        self.statements.iter()
+    }
+
+    /// Iterate over every statement and expression nested in the block, in
+    /// source order.
+    pub fn walk(&self) -> Walk<'_> {
+        Walk::new(self.iter().rev().map(AstNode::Statement).collect())
+    }
+
+    /// Iterate over every expression nested in the block, in source order.
+    pub fn exprs(&self) -> impl Iterator<Item = &Expression> {
+        self.walk().filter_map(AstNode::expression)
+    }
+}
+
+impl<'a> IntoIterator for &'a BlockStatement {
+    type Item = &'a Statement;
+    type IntoIter = slice::Iter<'a, Statement>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for BlockStatement {
+    type Item = Statement;
+    type IntoIter = vec::IntoIter<Statement>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.statements.into_iter()
+    }
 }
 
 impl Display for BlockStatement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for stmt in &self.statements {
-            write!(f, "{}", stmt)?;
-        }
-        Ok(())
+        render(f, Node::Block(self, 1), usize::MAX)
     }
 }
 
@@ -190,17 +427,568 @@ impl Program {
     pub fn is_empty(&self) -> bool {
         self.statements.is_empty()
     }
+    /// Iterate over the top-level statements.
+    pub fn iter(&self) -> slice::Iter<'_, Statement> {
+        self.statements.iter()
+    }
+    /// Iterate over every statement and expression in the program, in
+    /// source order.
+    pub fn walk(&self) -> Walk<'_> {
+        Walk::new(self.iter().rev().map(AstNode::Statement).collect())
+    }
+    /// Iterate over every expression in the program, in source order.
+    pub fn exprs(&self) -> impl Iterator<Item = &Expression> {
+        self.walk().filter_map(AstNode::expression)
+    }
+}
+
+impl<'a> IntoIterator for &'a Program {
+    type Item = &'a Statement;
+    type IntoIter = slice::Iter<'a, Statement>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for Program {
+    type Item = Statement;
+    type IntoIter = vec::IntoIter<Statement>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.statements.into_iter()
+    }
+}
+
+impl Program {
+    /// Parse `source` as a whole program, returning every error found if
+    /// there are any.
+    pub fn parse(source: &str) -> Result<Program, Vec<ParserError>> {
+        Parser::from_input(source)
+            .parse_program()
+            .map_err(|failure| failure.errors)
+    }
+
+    /// Display the program, eliding anything nested more than `max_depth`
+    /// expressions deep as `...`.
+    pub fn depth_limited(&self, max_depth: usize) -> DepthLimited<'_> {
+        DepthLimited {
+            node: Node::Program(self),
+            max_depth,
+        }
+    }
+
+    /// Render as Lisp-style s-expressions, one line per statement. Blocks
+    /// are `(block ...)`, so `let x = if (a) { 1 } else { 2 };` becomes
+    /// `(let x (if a (block 1) (block 2)))`.
+    pub fn to_sexpr(&self) -> String {
+        render_sexpr(Node::Program(self))
+    }
 }
 
 impl Display for Program {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for stmt in &self.statements {
-            write!(f, "{}\n", stmt)?;
+        render(f, Node::Program(self), usize::MAX)
+    }
+}
+
+/// Display adaptor returned by `depth_limited`.
+#[derive(Clone, Copy)]
+pub struct DepthLimited<'a> {
+    node: Node<'a>,
+    max_depth: usize,
+}
+
+impl Display for DepthLimited<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        render(f, self.node, self.max_depth)
+    }
+}
+
+/// A pending piece of output for `render`. Nodes carry their expression
+/// nesting depth, starting from 1.
+#[derive(Clone, Copy)]
+enum Node<'a> {
+    Text(&'a str),
+    Expr(&'a Expression, usize),
+    Stmt(&'a Statement, usize),
+    Block(&'a BlockStatement, usize),
+    Program(&'a Program),
+    Pattern(&'a Pattern),
+}
+
+/// The `if` statement an alternative block holds when it was parsed from
+/// `else if`, so it can be printed the same way.
+pub(crate) fn else_if(block: &BlockStatement) -> Option<&Statement> {
+    match block.statements.as_slice() {
+        [stmt @ Statement::Expression {
+            expr: Expression::If { .. },
+            ..
+        }] if block.token == TokenKind::If => Some(stmt),
+        _ => None,
+    }
+}
+
+/// A string literal as source, raw when `value` holds a `${` that would
+/// otherwise start an interpolation.
+pub(crate) fn quote_string(value: &str) -> String {
+    if value.contains("${") {
+        format!("r\"{}\"", value)
+    } else {
+        format!("\"{}\"", value)
+    }
+}
+
+/// Push a doc comment, one `///` line per line of `doc`.
+fn push_doc<'a>(stack: &mut Vec<Node<'a>>, doc: &'a Option<String>) {
+    if let Some(doc) = doc {
+        for line in doc.split('\n').rev() {
+            stack.push(Node::Text("\n"));
+            stack.push(Node::Text(line));
+            stack.push(Node::Text("/// "));
+        }
+    }
+}
+
+/// Push comma-separated expressions, in reverse.
+fn push_list<'a>(stack: &mut Vec<Node<'a>>, exprs: &'a [Expression], depth: usize) {
+    for (idx, expr) in exprs.iter().enumerate().rev() {
+        stack.push(Node::Expr(expr, depth));
+        if idx > 0 {
+            stack.push(Node::Text(", "));
         }
-        Ok(())
     }
 }
 
+/// Write a node using an explicit stack rather than recursion, so printing
+/// an arbitrarily deep AST cannot overflow the call stack.
+fn render(f: &mut fmt::Formatter, root: Node, max_depth: usize) -> fmt::Result {
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        match node {
+            Node::Text(text) => f.write_str(text)?,
+            Node::Expr(_, depth) if depth > max_depth => f.write_str("...")?,
+            Node::Expr(expr, depth) => match expr {
+                Expression::Identifier(name) => write!(f, "{}", name)?,
+                Expression::IntegerLiteral(value) => write!(f, "{}", value)?,
+                Expression::StringLiteral(value) => f.write_str(&quote_string(value))?,
+                Expression::Interpolated(parts) => {
+                    stack.push(Node::Text("\""));
+                    for part in parts.iter().rev() {
+                        match part {
+                            StringPart::Text(text) => stack.push(Node::Text(text)),
+                            StringPart::Expr(expr) => {
+                                stack.push(Node::Text("}"));
+                                stack.push(Node::Expr(expr, depth + 1));
+                                stack.push(Node::Text("${"));
+                            }
+                        }
+                    }
+                    stack.push(Node::Text("\""));
+                }
+                Expression::Boolean(value) => write!(f, "{}", value)?,
+                Expression::Nothing => (),
+                Expression::Prefix { operator, right } => {
+                    stack.push(Node::Text(")"));
+                    stack.push(Node::Expr(right, depth + 1));
+                    stack.push(Node::Text(&operator.literal));
+                    stack.push(Node::Text("("));
+                }
+                Expression::Infix {
+                    left,
+                    operator,
+                    right,
+                } => {
+                    stack.push(Node::Text(")"));
+                    stack.push(Node::Expr(right, depth + 1));
+                    stack.push(Node::Text(" "));
+                    stack.push(Node::Text(&operator.literal));
+                    stack.push(Node::Text(" "));
+                    stack.push(Node::Expr(left, depth + 1));
+                    stack.push(Node::Text("("));
+                }
+                Expression::If {
+                    condition,
+                    consequence,
+                    alternative,
+                } => {
+                    if let Some(alternative) = alternative {
+                        match else_if(alternative) {
+                            Some(stmt) => stack.push(Node::Stmt(stmt, depth + 1)),
+                            None => stack.push(Node::Block(alternative, depth + 1)),
+                        }
+                        stack.push(Node::Text(" else "));
+                    }
+                    stack.push(Node::Block(consequence, depth + 1));
+                    // Prefix and infix expressions bring their own parens.
+                    let bracketed = matches!(
+                        **condition,
+                        Expression::Prefix { .. } | Expression::Infix { .. }
+                    );
+                    stack.push(Node::Text(if bracketed { " " } else { ") " }));
+                    stack.push(Node::Expr(condition, depth + 1));
+                    stack.push(Node::Text(if bracketed { "if " } else { "if (" }));
+                }
+                Expression::Try {
+                    body,
+                    binding,
+                    handler,
+                } => {
+                    stack.push(Node::Block(handler, depth + 1));
+                    stack.push(Node::Text(") "));
+                    stack.push(Node::Text(&binding.0));
+                    stack.push(Node::Text(" catch ("));
+                    stack.push(Node::Block(body, depth + 1));
+                    stack.push(Node::Text("try "));
+                }
+                Expression::Function {
+                    parameters,
+                    body,
+                    doc,
+                } => {
+                    stack.push(Node::Block(body, depth + 1));
+                    stack.push(Node::Text(") "));
+                    for (idx, parameter) in parameters.iter().enumerate().rev() {
+                        stack.push(Node::Text(&parameter.0));
+                        if idx > 0 {
+                            stack.push(Node::Text(", "));
+                        }
+                    }
+                    stack.push(Node::Text("fn("));
+                    push_doc(&mut stack, doc);
+                }
+                Expression::Index { left, index } => {
+                    stack.push(Node::Text("])"));
+                    stack.push(Node::Expr(index, depth + 1));
+                    stack.push(Node::Text("["));
+                    stack.push(Node::Expr(left, depth + 1));
+                    stack.push(Node::Text("("));
+                }
+                Expression::Slice { left, start, end } => {
+                    stack.push(Node::Text("])"));
+                    if let Some(end) = end {
+                        stack.push(Node::Expr(end, depth + 1));
+                    }
+                    stack.push(Node::Text(":"));
+                    if let Some(start) = start {
+                        stack.push(Node::Expr(start, depth + 1));
+                    }
+                    stack.push(Node::Text("["));
+                    stack.push(Node::Expr(left, depth + 1));
+                    stack.push(Node::Text("("));
+                }
+                Expression::ArrayLiteral(elements) => {
+                    stack.push(Node::Text("]"));
+                    push_list(&mut stack, elements, depth + 1);
+                    stack.push(Node::Text("["));
+                }
+                Expression::Call {
+                    function,
+                    arguments,
+                } => {
+                    stack.push(Node::Text(")"));
+                    push_list(&mut stack, arguments, depth + 1);
+                    stack.push(Node::Text("("));
+                    stack.push(Node::Expr(function, depth + 1));
+                }
+                Expression::Range {
+                    start,
+                    end,
+                    inclusive,
+                } => {
+                    stack.push(Node::Text(")"));
+                    stack.push(Node::Expr(end, depth + 1));
+                    stack.push(Node::Text(if *inclusive { "..=" } else { ".." }));
+                    stack.push(Node::Expr(start, depth + 1));
+                    stack.push(Node::Text("("));
+                }
+            },
+            Node::Stmt(stmt, depth) => match stmt {
+                Statement::Let {
+                    token,
+                    name,
+                    value,
+                    doc,
+                } => {
+                    stack.push(Node::Text(";"));
+                    if *value != Expression::Nothing {
+                        stack.push(Node::Expr(value, depth));
+                        stack.push(Node::Text(" = "));
+                    }
+                    stack.push(Node::Text(&name.0));
+                    stack.push(Node::Text(" "));
+                    stack.push(Node::Text(&token.literal));
+                    push_doc(&mut stack, doc);
+                }
+                Statement::Destructure {
+                    token,
+                    pattern,
+                    value,
+                } => {
+                    stack.push(Node::Text(";"));
+                    stack.push(Node::Expr(value, depth));
+                    stack.push(Node::Text(" = "));
+                    stack.push(Node::Pattern(pattern));
+                    stack.push(Node::Text(" "));
+                    stack.push(Node::Text(&token.literal));
+                }
+                Statement::Return { token, expr } => {
+                    stack.push(Node::Text(";"));
+                    if *expr != Expression::Nothing {
+                        stack.push(Node::Expr(expr, depth));
+                        stack.push(Node::Text(" "));
+                    }
+                    stack.push(Node::Text(&token.literal));
+                }
+                Statement::Expression { expr, .. } => stack.push(Node::Expr(expr, depth)),
+            },
+            Node::Block(block, depth) => {
+                // Expression statements don't print a semicolon of their
+                // own, so one separates them from whatever follows.
+                let last = block.statements.len().saturating_sub(1);
+                stack.push(Node::Text("}"));
+                for (idx, stmt) in block.statements.iter().enumerate().rev() {
+                    stack.push(Node::Text(" "));
+                    if idx < last && matches!(stmt, Statement::Expression { .. }) {
+                        stack.push(Node::Text(";"));
+                    }
+                    stack.push(Node::Stmt(stmt, depth));
+                }
+                stack.push(Node::Text("{ "));
+            }
+            Node::Program(program) => {
+                // As in blocks, a semicolon stops the next line being read
+                // as a call or index of an expression statement.
+                let last = program.statements.len().saturating_sub(1);
+                for (idx, stmt) in program.statements.iter().enumerate().rev() {
+                    stack.push(Node::Text("\n"));
+                    if idx < last && matches!(stmt, Statement::Expression { .. }) {
+                        stack.push(Node::Text(";"));
+                    }
+                    stack.push(Node::Stmt(stmt, 1));
+                }
+            }
+            Node::Pattern(pattern) => match pattern {
+                Pattern::Identifier(name) => f.write_str(&name.0)?,
+                Pattern::Array(elements) => {
+                    stack.push(Node::Text("]"));
+                    for (idx, element) in elements.iter().enumerate().rev() {
+                        stack.push(Node::Pattern(element));
+                        if idx > 0 {
+                            stack.push(Node::Text(", "));
+                        }
+                    }
+                    stack.push(Node::Text("["));
+                }
+                Pattern::Hash(names) => {
+                    stack.push(Node::Text("}"));
+                    for (idx, name) in names.iter().enumerate().rev() {
+                        stack.push(Node::Text(&name.0));
+                        if idx > 0 {
+                            stack.push(Node::Text(", "));
+                        }
+                    }
+                    stack.push(Node::Text("{"));
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Render a node as an s-expression, with the same explicit stack as
+/// `render`. Expression depths are ignored.
+fn render_sexpr<'a>(root: Node<'a>) -> String {
+    let mut out = String::new();
+    let mut stack = vec![root];
+    // Push `(head child child ...)`, in reverse.
+    fn list<'a>(stack: &mut Vec<Node<'a>>, head: &'a str, children: Vec<Node<'a>>) {
+        stack.push(Node::Text(")"));
+        for child in children.into_iter().rev() {
+            stack.push(child);
+            stack.push(Node::Text(" "));
+        }
+        stack.push(Node::Text(head));
+        stack.push(Node::Text("("));
+    }
+    while let Some(node) = stack.pop() {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Expr(expr, depth) => match expr {
+                Expression::Identifier(name) => out.push_str(&name.0),
+                Expression::IntegerLiteral(value) => out.push_str(&value.to_string()),
+                Expression::StringLiteral(value) => out.push_str(&quote_string(value)),
+                Expression::Interpolated(parts) => {
+                    stack.push(Node::Text(")"));
+                    for part in parts.iter().rev() {
+                        match part {
+                            StringPart::Text(text) => {
+                                stack.push(Node::Text("\""));
+                                stack.push(Node::Text(text));
+                                stack.push(Node::Text("\""));
+                            }
+                            StringPart::Expr(expr) => stack.push(Node::Expr(expr, depth)),
+                        }
+                        stack.push(Node::Text(" "));
+                    }
+                    stack.push(Node::Text("(interpolate"));
+                }
+                Expression::Boolean(value) => out.push_str(&value.to_string()),
+                Expression::Nothing => out.push_str("nil"),
+                Expression::Prefix { operator, right } => list(
+                    &mut stack,
+                    &operator.literal,
+                    vec![Node::Expr(right, depth)],
+                ),
+                Expression::Infix {
+                    left,
+                    operator,
+                    right,
+                } => list(
+                    &mut stack,
+                    &operator.literal,
+                    vec![Node::Expr(left, depth), Node::Expr(right, depth)],
+                ),
+                Expression::If {
+                    condition,
+                    consequence,
+                    alternative,
+                } => {
+                    let mut children = vec![
+                        Node::Expr(condition, depth),
+                        Node::Block(consequence, depth),
+                    ];
+                    children.extend(alternative.iter().map(|block| Node::Block(block, depth)));
+                    list(&mut stack, "if", children);
+                }
+                Expression::Try {
+                    body,
+                    binding,
+                    handler,
+                } => {
+                    stack.push(Node::Text("))"));
+                    stack.push(Node::Block(handler, depth));
+                    stack.push(Node::Text(" "));
+                    stack.push(Node::Text(&binding.0));
+                    stack.push(Node::Text(" (catch "));
+                    stack.push(Node::Block(body, depth));
+                    stack.push(Node::Text("(try "));
+                }
+                Expression::Function {
+                    parameters, body, ..
+                } => {
+                    stack.push(Node::Text(")"));
+                    stack.push(Node::Block(body, depth));
+                    stack.push(Node::Text(") "));
+                    for (idx, parameter) in parameters.iter().enumerate().rev() {
+                        stack.push(Node::Text(&parameter.0));
+                        if idx > 0 {
+                            stack.push(Node::Text(" "));
+                        }
+                    }
+                    stack.push(Node::Text("(fn ("));
+                }
+                Expression::Index { left, index } => list(
+                    &mut stack,
+                    "index",
+                    vec![Node::Expr(left, depth), Node::Expr(index, depth)],
+                ),
+                Expression::Slice { left, start, end } => {
+                    let bound = |bound: &'a Option<Box<Expression>>| match bound {
+                        Some(bound) => Node::Expr(bound, depth),
+                        None => Node::Text("nil"),
+                    };
+                    list(
+                        &mut stack,
+                        "slice",
+                        vec![Node::Expr(left, depth), bound(start), bound(end)],
+                    )
+                }
+                Expression::ArrayLiteral(elements) => list(
+                    &mut stack,
+                    "array",
+                    elements
+                        .iter()
+                        .map(|element| Node::Expr(element, depth))
+                        .collect(),
+                ),
+                Expression::Range {
+                    start,
+                    end,
+                    inclusive,
+                } => list(
+                    &mut stack,
+                    if *inclusive { "..=" } else { ".." },
+                    vec![Node::Expr(start, depth), Node::Expr(end, depth)],
+                ),
+                Expression::Call {
+                    function,
+                    arguments,
+                } => list(
+                    &mut stack,
+                    "call",
+                    Some(function.as_ref())
+                        .into_iter()
+                        .chain(arguments)
+                        .map(|expr| Node::Expr(expr, depth))
+                        .collect(),
+                ),
+            },
+            Node::Stmt(stmt, depth) => match stmt {
+                Statement::Let { name, value, .. } => {
+                    let mut children = vec![Node::Text(&name.0)];
+                    if *value != Expression::Nothing {
+                        children.push(Node::Expr(value, depth));
+                    }
+                    list(&mut stack, "let", children);
+                }
+                Statement::Destructure { pattern, value, .. } => list(
+                    &mut stack,
+                    "let",
+                    vec![Node::Pattern(pattern), Node::Expr(value, depth)],
+                ),
+                Statement::Return { expr, .. } => {
+                    let children = match expr {
+                        Expression::Nothing => vec![],
+                        _ => vec![Node::Expr(expr, depth)],
+                    };
+                    list(&mut stack, "return", children);
+                }
+                Statement::Expression { expr, .. } => stack.push(Node::Expr(expr, depth)),
+            },
+            Node::Block(block, depth) => list(
+                &mut stack,
+                "block",
+                block
+                    .statements
+                    .iter()
+                    .map(|stmt| Node::Stmt(stmt, depth))
+                    .collect(),
+            ),
+            Node::Program(program) => {
+                for stmt in program.statements.iter().rev() {
+                    stack.push(Node::Text("\n"));
+                    stack.push(Node::Stmt(stmt, 1));
+                }
+            }
+            Node::Pattern(pattern) => match pattern {
+                Pattern::Identifier(name) => out.push_str(&name.0),
+                Pattern::Array(elements) => list(
+                    &mut stack,
+                    "array",
+                    elements.iter().map(Node::Pattern).collect(),
+                ),
+                Pattern::Hash(names) => list(
+                    &mut stack,
+                    "hash",
+                    names.iter().map(|name| Node::Text(&name.0)).collect(),
+                ),
+            },
+        }
+    }
+    out
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -208,9 +996,10 @@ mod test {
     #[test]
     fn test_display_let_statement() {
         let stmt = Statement::Let {
-            token: Token::Let,
+            token: Token::from(TokenKind::Let),
             name: Identifier::new("x"),
             value: Expression::Identifier(Identifier::new("y")),
+            doc: None,
         };
         assert_eq!(format!("{}", stmt), "let x = y;");
     }
@@ -218,7 +1007,7 @@ mod test {
     #[test]
     fn test_display_return_statement() {
         let stmt = Statement::Return {
-            token: Token::Return,
+            token: Token::from(TokenKind::Return),
             expr: Expression::Nothing,
         };
         assert_eq!(format!("{}", stmt), "return;");
@@ -229,12 +1018,13 @@ mod test {
         let prog = Program {
             statements: vec![
                 Statement::Let {
-                    token: Token::Let,
+                    token: Token::from(TokenKind::Let),
                     name: Identifier::new("x"),
                     value: Expression::Identifier(Identifier::new("y")),
+                    doc: None,
                 },
                 Statement::Return {
-                    token: Token::Return,
+                    token: Token::from(TokenKind::Return),
                     expr: Expression::Nothing,
                 },
             ],
@@ -245,19 +1035,322 @@ mod test {
     #[test]
     fn test_display_prefix_expression() {
         let stmt = Expression::Prefix {
-            operator: Token::Minus,
+            operator: Token::from(TokenKind::Minus),
             right: Box::new(Expression::IntegerLiteral(5)),
         };
         assert_eq!(format!("{}", stmt), "(-5)");
     }
 
+    #[test]
+    fn test_display_if_expression() {
+        let mut consequence = BlockStatement::new(Token::from(TokenKind::LBrace));
+        consequence.statements.push(Statement::Expression {
+            token: Token::make_ident("x"),
+            expr: Expression::new_ident("x"),
+        });
+        let expr = Expression::If {
+            condition: Box::new(Expression::new_infix(
+                Identifier::new("x"),
+                Token::from(TokenKind::Lt),
+                Identifier::new("y"),
+            )),
+            consequence: consequence.clone(),
+            alternative: Some(consequence),
+        };
+        assert_eq!(format!("{}", expr), "if (x < y) { x } else { x }");
+    }
+
+    #[test]
+    fn test_display_index_expressions() {
+        let string = || Box::new(Expression::StringLiteral("hello".to_owned()));
+        let index = Expression::Index {
+            left: string(),
+            index: Box::new(Expression::IntegerLiteral(1)),
+        };
+        assert_eq!(index.to_string(), "(\"hello\"[1])");
+        assert_eq!(index.to_sexpr(), "(index \"hello\" 1)");
+        let slice = Expression::Slice {
+            left: string(),
+            start: Some(Box::new(Expression::IntegerLiteral(1))),
+            end: Some(Box::new(Expression::new_ident("n"))),
+        };
+        assert_eq!(slice.to_string(), "(\"hello\"[1:n])");
+        assert_eq!(slice.to_sexpr(), "(slice \"hello\" 1 n)");
+        let slice = Expression::Slice {
+            left: string(),
+            start: None,
+            end: Some(Box::new(Expression::IntegerLiteral(2))),
+        };
+        assert_eq!(slice.to_string(), "(\"hello\"[:2])");
+        assert_eq!(slice.to_sexpr(), "(slice \"hello\" nil 2)");
+        let array = Expression::ArrayLiteral(vec![
+            Expression::IntegerLiteral(1),
+            Expression::new_prefix(Token::from(TokenKind::Minus), Expression::IntegerLiteral(2)),
+        ]);
+        assert_eq!(array.to_string(), "[1, (-2)]");
+        assert_eq!(array.to_sexpr(), "(array 1 (- 2))");
+        assert_eq!(Expression::ArrayLiteral(vec![]).to_string(), "[]");
+        let call = Expression::Call {
+            function: Box::new(Expression::new_ident("add")),
+            arguments: vec![
+                Expression::IntegerLiteral(1),
+                Expression::new_infix(2, Token::from(TokenKind::Asterisk), 3),
+            ],
+        };
+        assert_eq!(call.to_string(), "add(1, (2 * 3))");
+        assert_eq!(call.to_sexpr(), "(call add 1 (* 2 3))");
+        let range = Expression::Range {
+            start: Box::new(Expression::IntegerLiteral(1)),
+            end: Box::new(Expression::new_ident("n")),
+            inclusive: true,
+        };
+        assert_eq!(range.to_string(), "(1..=n)");
+        assert_eq!(range.to_sexpr(), "(..= 1 n)");
+        let interpolated = Expression::Interpolated(vec![
+            StringPart::Text("a ".to_owned()),
+            StringPart::Expr(Expression::new_infix(1, Token::from(TokenKind::Plus), 2)),
+            StringPart::Expr(Expression::StringLiteral("b".to_owned())),
+        ]);
+        assert_eq!(interpolated.to_string(), "\"a ${(1 + 2)}${\"b\"}\"");
+        assert_eq!(
+            interpolated.to_sexpr(),
+            "(interpolate \"a \" (+ 1 2) \"b\")"
+        );
+    }
+
+    #[test]
+    fn test_display_destructure() {
+        let pattern = Pattern::Array(vec![
+            Pattern::Identifier(Identifier::new("a")),
+            Pattern::Hash(vec![Identifier::new("x"), Identifier::new("y")]),
+            Pattern::Array(vec![]),
+        ]);
+        let names: Vec<&str> = pattern.bindings().iter().map(|b| b.0.as_str()).collect();
+        assert_eq!(names, vec!["a", "x", "y"]);
+        let stmt = Statement::Destructure {
+            token: Token::from(TokenKind::Let),
+            pattern,
+            value: Expression::new_ident("v"),
+        };
+        assert_eq!(stmt.to_string(), "let [a, {x, y}, []] = v;");
+        assert_eq!(stmt.to_sexpr(), "(let (array a (hash x y) (array)) v)");
+    }
+
+    #[test]
+    fn test_display_try_expression() {
+        let mut body = BlockStatement::new(Token::from(TokenKind::LBrace));
+        body.statements.push(Statement::Expression {
+            token: Token::make_ident("x"),
+            expr: Expression::new_ident("x"),
+        });
+        let expr = Expression::Try {
+            body: body.clone(),
+            binding: Identifier::new("e"),
+            handler: body,
+        };
+        assert_eq!(format!("{}", expr), "try { x } catch (e) { x }");
+    }
+
+    #[test]
+    fn test_to_sexpr() {
+        let value = Expression::new_infix(
+            Expression::IntegerLiteral(1),
+            Token::from(TokenKind::Plus),
+            Expression::new_infix(
+                Expression::IntegerLiteral(2),
+                Token::from(TokenKind::Asterisk),
+                Expression::new_prefix(Token::from(TokenKind::Minus), Expression::new_ident("y")),
+            ),
+        );
+        let mut body = BlockStatement::new(Token::from(TokenKind::LBrace));
+        body.statements.push(Statement::Return {
+            token: Token::from(TokenKind::Return),
+            expr: Expression::new_ident("a"),
+        });
+        let prog = Program {
+            statements: vec![
+                Statement::Let {
+                    token: Token::from(TokenKind::Let),
+                    name: Identifier::new("x"),
+                    value,
+                    doc: None,
+                },
+                Statement::Expression {
+                    token: Token::from(TokenKind::Function),
+                    expr: Expression::Function {
+                        parameters: vec![Identifier::new("a"), Identifier::new("b")],
+                        body: body.clone(),
+                        doc: None,
+                    },
+                },
+                Statement::Expression {
+                    token: Token::from(TokenKind::If),
+                    expr: Expression::If {
+                        condition: Box::new(Expression::Boolean(true)),
+                        consequence: BlockStatement::new(Token::from(TokenKind::LBrace)),
+                        alternative: Some(body.clone()),
+                    },
+                },
+                Statement::Expression {
+                    token: Token::from(TokenKind::Try),
+                    expr: Expression::Try {
+                        body: body.clone(),
+                        binding: Identifier::new("e"),
+                        handler: body,
+                    },
+                },
+                Statement::Return {
+                    token: Token::from(TokenKind::Return),
+                    expr: Expression::Nothing,
+                },
+            ],
+        };
+        assert_eq!(
+            prog.to_sexpr(),
+            "(let x (+ 1 (* 2 (- y))))\n\
+             (fn (a b) (block (return a)))\n\
+             (if true (block) (block (return a)))\n\
+             (try (block (return a)) (catch e (block (return a))))\n\
+             (return)\n"
+        );
+    }
+
+    #[test]
+    fn test_display_depth_limited() {
+        let expr = Expression::new_infix(
+            Expression::new_prefix(
+                Token::from(TokenKind::Minus),
+                Expression::new_prefix(Token::from(TokenKind::Bang), true),
+            ),
+            Token::from(TokenKind::Plus),
+            Expression::IntegerLiteral(1),
+        );
+        assert_eq!(format!("{}", expr.depth_limited(4)), "((-(!true)) + 1)");
+        assert_eq!(format!("{}", expr.depth_limited(3)), "((-(!...)) + 1)");
+        assert_eq!(format!("{}", expr.depth_limited(2)), "((-...) + 1)");
+        assert_eq!(format!("{}", expr.depth_limited(0)), "...");
+
+        let prog = Program {
+            statements: vec![Statement::Let {
+                token: Token::from(TokenKind::Let),
+                name: Identifier::new("x"),
+                value: expr,
+                doc: None,
+            }],
+        };
+        assert_eq!(
+            format!("{}", prog.depth_limited(1)),
+            "let x = (... + ...);\n"
+        );
+    }
+
+    #[test]
+    fn test_display_deep_expression() {
+        let depth = 100_000;
+        let mut expr = Expression::IntegerLiteral(1);
+        for _ in 0..depth {
+            expr = Expression::new_prefix(Token::from(TokenKind::Minus), expr);
+        }
+        let output = format!("{}", expr);
+        assert_eq!(output.len(), depth * 3 + 1);
+        assert!(output.starts_with("(-(-"));
+        assert!(output.contains("(-1))"));
+    }
+
+    #[test]
+    fn test_drop_deep_expression() {
+        let mut expr = Expression::IntegerLiteral(1);
+        for idx in 0..1_000_000 {
+            expr = if idx % 2 == 0 {
+                Expression::new_prefix(Token::from(TokenKind::Minus), expr)
+            } else {
+                Expression::new_infix(
+                    expr,
+                    Token::from(TokenKind::Plus),
+                    Expression::IntegerLiteral(2),
+                )
+            };
+        }
+        drop(expr);
+    }
+
+    #[test]
+    fn test_drop_deep_if_statements() {
+        let mut expr = Expression::Boolean(true);
+        for _ in 0..1_000_000 {
+            let mut consequence = BlockStatement::new(Token::from(TokenKind::LBrace));
+            consequence.statements.push(Statement::Expression {
+                token: Token::from(TokenKind::If),
+                expr,
+            });
+            expr = Expression::If {
+                condition: Box::new(Expression::Boolean(true)),
+                consequence,
+                alternative: None,
+            };
+        }
+        let stmt = Statement::Let {
+            token: Token::from(TokenKind::Let),
+            name: Identifier::new("x"),
+            value: expr,
+            doc: None,
+        };
+        drop(stmt);
+    }
+
     #[test]
     fn test_display_infix_expression() {
         let stmt = Expression::Infix {
             left: Box::new(Expression::IntegerLiteral(5)),
-            operator: Token::Plus,
+            operator: Token::from(TokenKind::Plus),
             right: Box::new(Expression::IntegerLiteral(5)),
         };
         assert_eq!(format!("{}", stmt), "(5 + 5)");
     }
+
+    #[test]
+    fn test_program_parse() {
+        let program = Program::parse("let x = 1; x + 2").unwrap();
+        assert_eq!(program.len(), 2);
+
+        let errors = Program::parse("let = 1;").unwrap_err();
+        assert!(matches!(
+            errors[..],
+            [
+                ParserError::ExpectedIdent(Token {
+                    kind: TokenKind::Assign,
+                    ..
+                }),
+                ..
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_expression_parse() {
+        let expr = Expression::parse("1 + 2 * 3;").unwrap();
+        assert_eq!(expr.to_string(), "(1 + (2 * 3))");
+
+        assert!(matches!(
+            Expression::parse("1 + 2 3"),
+            Err(ParserError::ExpectedToken {
+                expected: TokenKind::EOF,
+                saw: Token {
+                    kind: TokenKind::Int,
+                    ..
+                },
+            })
+        ));
+        assert!(matches!(
+            Expression::parse("let x = 1"),
+            Err(ParserError::UnhandledPrefix(Token {
+                kind: TokenKind::Let,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            Expression::parse("1 + @"),
+            Err(ParserError::Lexer(_))
+        ));
+    }
 }
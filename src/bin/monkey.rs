@@ -0,0 +1,224 @@
+use interp::diagnostic::{Diagnostic, Severity};
+use interp::parser::Parser;
+use interp::repl::{self, OutputMode, View};
+use interp::version;
+use interp::{doc, explain, lint};
+use std::fs::{self, File};
+use std::io::{stdin, stdout, BufReader};
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "usage: monkey [--version [--verbose]] [repl [--json] [--tokens] | [--error-format=text|json] [--deny-warnings] (check [--emit=sexpr] | lint) <file|glob>... | explain <code> | doc [--json] <file>]";
+
+fn main() -> Result<(), std::io::Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let flag = |name: &str| args.iter().any(|arg| arg == name);
+
+    if flag("--version") || flag("-V") {
+        println!("{}", version::version_string(flag("--verbose")));
+        return Ok(());
+    }
+
+    let mode = if flag("--json") {
+        OutputMode::Json
+    } else {
+        OutputMode::Text
+    };
+
+    let error_format = match args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--error-format="))
+    {
+        None | Some("text") => OutputMode::Text,
+        Some("json") => OutputMode::Json,
+        Some(format) => {
+            eprintln!("monkey: unknown error format '{}'\n{}", format, USAGE);
+            process::exit(2);
+        }
+    };
+
+    // What `check` prints for files which parse.
+    let emit_sexpr = match args.iter().find_map(|arg| arg.strip_prefix("--emit=")) {
+        None => false,
+        Some("sexpr") => true,
+        Some(emit) => {
+            eprintln!("monkey: unknown emit format '{}'\n{}", emit, USAGE);
+            process::exit(2);
+        }
+    };
+
+    // Whether warnings fail `check`. The linter only reports warnings, so
+    // any it finds always fail `lint`.
+    let deny_warnings = flag("--deny-warnings");
+
+    let mut operands = args
+        .iter()
+        .filter(|arg| !arg.starts_with('-'))
+        .map(String::as_str);
+    match operands.next() {
+        None | Some("repl") => {
+            if mode == OutputMode::Text {
+                println!("Welcome to the Monkey programming language");
+            }
+            let view = if flag("--tokens") {
+                View::Tokens
+            } else {
+                View::Program
+            };
+            repl::start_with_view(&mut stdin(), &mut stdout(), mode, view)
+        }
+        Some(command @ "check") | Some(command @ "lint") => {
+            let patterns: Vec<&str> = operands.collect();
+            if patterns.is_empty() {
+                eprintln!("monkey: {} needs at least one file\n{}", command, USAGE);
+                process::exit(2);
+            }
+            let ok = for_each_file(&patterns, |name, path, reader| {
+                let mut parser = Parser::from_reader(reader);
+                let parsed = parser.parse_program();
+                // The parser's own warnings come before the linter's.
+                let mut diagnostics = parser.diagnostics();
+                if let (Ok(program), "lint") = (&parsed, command) {
+                    diagnostics.extend(lint::lint(program).iter().map(Diagnostic::from));
+                }
+                if !report_file(&diagnostics, name, path, error_format) {
+                    return false;
+                }
+                let failed = diagnostics
+                    .iter()
+                    .any(|diagnostic| match diagnostic.severity {
+                        Severity::Error => true,
+                        Severity::Warning => deny_warnings || command == "lint",
+                        Severity::Note => false,
+                    });
+                if emit_sexpr && command == "check" && !failed {
+                    let program = parsed.unwrap_or_else(|failure| failure.program);
+                    print!("{}", program.to_sexpr());
+                }
+                !failed
+            });
+            if !ok {
+                process::exit(1);
+            }
+            Ok(())
+        }
+        Some("doc") => match operands.next() {
+            Some(path) => {
+                let mut parser = Parser::from_reader(BufReader::new(File::open(path)?));
+                let program = match parser.parse_program() {
+                    Ok(program) => program,
+                    Err(_) => {
+                        report_file(&parser.diagnostics(), path, Path::new(path), error_format);
+                        process::exit(1);
+                    }
+                };
+                let functions = doc::functions(&program);
+                match mode {
+                    OutputMode::Text => print!("{}", doc::to_markdown(path, &functions)),
+                    OutputMode::Json => println!("{}", doc::to_json(&functions)),
+                }
+                Ok(())
+            }
+            None => {
+                eprintln!("monkey: doc needs a file\n{}", USAGE);
+                process::exit(2);
+            }
+        },
+        Some("explain") => match operands.next() {
+            Some(code) => match explain::explain(code) {
+                Some(explanation) => {
+                    println!("{}", explanation);
+                    Ok(())
+                }
+                None => {
+                    eprintln!("monkey: '{}' is not a known error code", code);
+                    process::exit(1);
+                }
+            },
+            None => {
+                eprintln!("monkey: explain needs an error code\n{}", USAGE);
+                process::exit(2);
+            }
+        },
+        Some(arg) => {
+            eprintln!("monkey: unrecognised argument '{}'\n{}", arg, USAGE);
+            process::exit(2);
+        }
+    }
+}
+
+/// Open every file matching `patterns` and pass its name, path and a reader
+/// over it to `check`, which returns false if the file has problems.
+/// Returns false if any file had problems or could not be opened.
+fn for_each_file(
+    patterns: &[&str],
+    mut check: impl FnMut(&str, &Path, BufReader<File>) -> bool,
+) -> bool {
+    let mut ok = true;
+    for pattern in patterns {
+        let paths = match glob::glob(pattern) {
+            Ok(paths) => paths,
+            Err(err) => {
+                eprintln!("monkey: bad pattern '{}': {}", pattern, err);
+                ok = false;
+                continue;
+            }
+        };
+        let mut matched = false;
+        for path in paths {
+            matched = true;
+            let path = match path {
+                Ok(path) => path,
+                Err(err) => {
+                    eprintln!("monkey: {}", err);
+                    ok = false;
+                    continue;
+                }
+            };
+            let name = path.display().to_string();
+            match File::open(&path) {
+                Ok(file) => ok &= check(&name, &path, BufReader::new(file)),
+                Err(err) => {
+                    eprintln!("monkey: {}: {}", name, err);
+                    ok = false;
+                }
+            }
+        }
+        if !matched {
+            eprintln!("monkey: no files match '{}'", pattern);
+            ok = false;
+        }
+    }
+    ok
+}
+
+/// Report the diagnostics for the file at `path`, which is parsed as it
+/// is read. Spans only become lines and columns against the text, so it is
+/// read whole only if there is something to report. Returns false if it
+/// couldn't be read again.
+fn report_file(diagnostics: &[Diagnostic], name: &str, path: &Path, format: OutputMode) -> bool {
+    if diagnostics.is_empty() {
+        return true;
+    }
+    match fs::read_to_string(path) {
+        Ok(source) => {
+            report(diagnostics, name, &source, format);
+            true
+        }
+        Err(err) => {
+            eprintln!("monkey: {}: {}", name, err);
+            false
+        }
+    }
+}
+
+/// Print diagnostics for humans on stderr, or as one JSON object per line
+/// on stdout.
+fn report(diagnostics: &[Diagnostic], name: &str, source: &str, format: OutputMode) {
+    for diagnostic in diagnostics {
+        match format {
+            OutputMode::Text => eprintln!("{}", diagnostic.render(name, source)),
+            OutputMode::Json => println!("{}", diagnostic.to_json(name, source)),
+        }
+    }
+}
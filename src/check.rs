@@ -0,0 +1,35 @@
+//! Parse-only validation of Monkey source, as run by `monkey check`.
+
+use crate::diagnostic::Diagnostic;
+use crate::parser::Parser;
+
+/// Parse `source` without evaluating it, returning a diagnostic for each
+/// error found.
+pub fn check_source(source: &str) -> Vec<Diagnostic> {
+    let mut parser = Parser::from_input(source);
+    // The spans of the errors are only kept on the parser.
+    let _ = parser.parse_program();
+    parser.diagnostics()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_source() {
+        assert!(check_source("let x = 5;\nx + 1;\n").is_empty());
+        let source = "let x = 5;\nlet y 10;\n";
+        let rendered: Vec<String> = check_source(source)
+            .iter()
+            .map(|diagnostic| diagnostic.render("bad.monkey", source))
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "bad.monkey:2:7: error[P0001]: Expected next token to be Assign, got Int(\"10\") instead\n  \
+                 help: insert `=`"
+            ]
+        );
+    }
+}
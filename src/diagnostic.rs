@@ -0,0 +1,192 @@
+//! A common shape for everything the tools report about a source file, with
+//! human-readable and JSON renderings.
+
+use crate::json;
+use crate::lexer::LexerError;
+use crate::lint::{Lint, LintKind};
+use crate::span::Span;
+use std::fmt::{self, Display};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    /// Extra information, which is never a problem by itself.
+    Note,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub code: Option<&'static str>,
+    pub severity: Severity,
+    pub message: String,
+    pub span: Option<Span>,
+    /// A short description of how to fix the problem.
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: String) -> Self {
+        Diagnostic::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: String) -> Self {
+        Diagnostic::new(Severity::Warning, message)
+    }
+
+    pub fn note(message: String) -> Self {
+        Diagnostic::new(Severity::Note, message)
+    }
+
+    fn new(severity: Severity, message: String) -> Self {
+        Diagnostic {
+            code: None,
+            severity,
+            message,
+            span: None,
+            suggestion: None,
+        }
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: String) -> Self {
+        self.suggestion = Some(suggestion);
+        self
+    }
+
+    /// Render as `file:line:column: severity[code]: message`, followed by
+    /// a `help:` line if there is a suggestion.
+    pub fn render(&self, file: &str, source: &str) -> String {
+        let mut out = file.to_owned();
+        if let Some(span) = self.span {
+            let (line, column) = span.location(source);
+            out += &format!(":{}:{}", line, column);
+        }
+        out += &format!(": {}", self.severity);
+        if let Some(code) = self.code {
+            out += &format!("[{}]", code);
+        }
+        out += &format!(": {}", self.message);
+        if let Some(suggestion) = &self.suggestion {
+            out += &format!("\n  help: {}", suggestion);
+        }
+        out
+    }
+
+    /// Render as a single-line JSON object. Absent fields are `null`.
+    pub fn to_json(&self, file: &str, source: &str) -> String {
+        let or_null = |value: Option<String>| value.unwrap_or_else(|| "null".to_owned());
+        let span = self.span.map(|span| {
+            let (line, column) = span.location(source);
+            json::object(&[
+                ("start", span.start.to_string()),
+                ("end", span.end.to_string()),
+                ("line", line.to_string()),
+                ("column", column.to_string()),
+            ])
+        });
+        json::object(&[
+            ("type", json::quote("diagnostic")),
+            ("code", or_null(self.code.map(json::quote))),
+            ("severity", json::quote(&self.severity.to_string())),
+            ("message", json::quote(&self.message)),
+            ("file", json::quote(file)),
+            ("span", or_null(span)),
+            (
+                "suggestion",
+                or_null(self.suggestion.as_deref().map(json::quote)),
+            ),
+        ])
+    }
+}
+
+impl From<&LexerError> for Diagnostic {
+    fn from(err: &LexerError) -> Self {
+        let diagnostic = Diagnostic::error(err.to_string()).with_code(err.code());
+        match err.suggestion() {
+            Some(suggestion) => diagnostic.with_suggestion(suggestion),
+            None => diagnostic,
+        }
+    }
+}
+
+impl From<&Lint> for Diagnostic {
+    fn from(lint: &Lint) -> Self {
+        let suggestion = match lint.kind {
+            LintKind::UnusedBinding => "remove the binding, or use it",
+            LintKind::UnreachableCode => "remove the statements after `return`",
+            LintKind::ConstantCondition => "remove the `if`, keeping the branch which runs",
+            LintKind::ShadowedBinding => "rename one of the bindings",
+            LintKind::ShadowedConstant => "rename the binding, so the constant keeps its name",
+            LintKind::UnknownIdentifier => "bind the name with `let`, or check its spelling",
+        };
+        Diagnostic::warning(lint.message.clone())
+            .with_code(lint.kind.name())
+            .with_span(lint.span)
+            .with_suggestion(
+                lint.suggestion
+                    .clone()
+                    .unwrap_or_else(|| suggestion.to_owned()),
+            )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let source = "let x = 1;\nlet = 2;";
+        let diagnostic = Diagnostic::error("bad let".to_owned()).with_span(Span::new(15, 16));
+        assert_eq!(
+            diagnostic.render("a.monkey", source),
+            "a.monkey:2:5: error: bad let"
+        );
+
+        let diagnostic = Diagnostic::warning("unused".to_owned())
+            .with_code("unused-binding")
+            .with_suggestion("remove it".to_owned());
+        assert_eq!(
+            diagnostic.render("a.monkey", source),
+            "a.monkey: warning[unused-binding]: unused\n  help: remove it"
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let source = "let x = 1;\nlet = 2;";
+        let diagnostic = Diagnostic::error("bad let".to_owned()).with_span(Span::new(15, 16));
+        assert_eq!(
+            diagnostic.to_json("a.monkey", source),
+            r#"{"type":"diagnostic","code":null,"severity":"error","message":"bad let","file":"a.monkey","span":{"start":15,"end":16,"line":2,"column":5},"suggestion":null}"#
+        );
+
+        let diagnostic = Diagnostic::warning("unused".to_owned())
+            .with_code("unused-binding")
+            .with_suggestion("remove it".to_owned());
+        assert_eq!(
+            diagnostic.to_json("a.monkey", source),
+            r#"{"type":"diagnostic","code":"unused-binding","severity":"warning","message":"unused","file":"a.monkey","span":null,"suggestion":"remove it"}"#
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! Documentation of a script's top-level functions, as rendered by
+//! `monkey doc`.
+
+use crate::ast::{Expression, Program, Statement};
+use crate::json;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionDoc {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub doc: Option<String>,
+}
+
+/// Every top-level `let` bound to a function literal, in source order. The
+/// binding's doc comment is used, falling back to the function's own.
+pub fn functions(program: &Program) -> Vec<FunctionDoc> {
+    program
+        .statements
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Statement::Let {
+                name,
+                value:
+                    Expression::Function {
+                        parameters,
+                        doc: function_doc,
+                        ..
+                    },
+                doc,
+                ..
+            } => Some(FunctionDoc {
+                name: name.0.clone(),
+                parameters: parameters.iter().map(|p| p.0.clone()).collect(),
+                doc: doc.clone().or_else(|| function_doc.clone()),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Render as a Markdown document headed by `title`, with a section per
+/// function.
+pub fn to_markdown(title: &str, functions: &[FunctionDoc]) -> String {
+    let mut out = format!("# {}\n", title);
+    for function in functions {
+        out += &format!(
+            "\n## `{}({})`\n",
+            function.name,
+            function.parameters.join(", ")
+        );
+        if let Some(doc) = &function.doc {
+            out += &format!("\n{}\n", doc);
+        }
+    }
+    out
+}
+
+/// Render as a JSON array with an object per function.
+pub fn to_json(functions: &[FunctionDoc]) -> String {
+    let functions: Vec<String> = functions
+        .iter()
+        .map(|function| {
+            let parameters: Vec<String> =
+                function.parameters.iter().map(|p| json::quote(p)).collect();
+            json::object(&[
+                ("name", json::quote(&function.name)),
+                ("parameters", json::array(&parameters)),
+                (
+                    "doc",
+                    function
+                        .doc
+                        .as_deref()
+                        .map_or_else(|| "null".to_owned(), json::quote),
+                ),
+            ])
+        })
+        .collect();
+    json::array(&functions)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn functions_for(input: &str) -> Vec<FunctionDoc> {
+        let mut parser = Parser::from_input(input);
+        let program = parser.parse_program().unwrap();
+        assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+        functions(&program)
+    }
+
+    #[test]
+    fn test_functions() {
+        let functions = functions_for(
+            "/// Add two numbers.\nlet add = fn(a, b) { a + b };\n\
+             /// Not a function.\nlet x = 1;\n\
+             let id = /// Identity.\nfn(x) { x };\n\
+             let f = fn() { /// Nested.\nlet g = fn() { }; };",
+        );
+        assert_eq!(
+            functions,
+            vec![
+                FunctionDoc {
+                    name: "add".to_owned(),
+                    parameters: vec!["a".to_owned(), "b".to_owned()],
+                    doc: Some("Add two numbers.".to_owned()),
+                },
+                FunctionDoc {
+                    name: "id".to_owned(),
+                    parameters: vec!["x".to_owned()],
+                    doc: Some("Identity.".to_owned()),
+                },
+                FunctionDoc {
+                    name: "f".to_owned(),
+                    parameters: vec![],
+                    doc: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rendering() {
+        let functions = functions_for("/// Add.\nlet add = fn(a, b) { a + b };\nlet f = fn() { };");
+        assert_eq!(
+            to_markdown("math.monkey", &functions),
+            "# math.monkey\n\n## `add(a, b)`\n\nAdd.\n\n## `f()`\n"
+        );
+        assert_eq!(
+            to_json(&functions),
+            r#"[{"name":"add","parameters":["a","b"],"doc":"Add."},{"name":"f","parameters":[],"doc":null}]"#
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Long-form descriptions of error and warning codes, printed by `monkey explain`.
+
+/// Every error and warning code with its explanation, in code order.
+pub const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        "L0001",
+        "\
+The input is larger than the lexer's size limit.
+
+To protect against unbounded memory use, the lexer refuses input over a
+configured size (16 MiB by default) before reading any of it.
+
+Split the program into smaller files, or raise `max_input_len` in the
+`LexerLimits` passed to `Lexer::with_limits` when embedding the
+interpreter.",
+    ),
+    (
+        "L0002",
+        "\
+An identifier is longer than the lexer's length limit.
+
+Names are limited to 1024 bytes by default. This almost always means the
+input isn't Monkey source at all, or that a separator is missing:
+
+    let xxxxxxxx...xxxxxxxx = 1;
+
+Use a shorter name, or raise `max_identifier_len` in `LexerLimits`.",
+    ),
+    (
+        "L0003",
+        "\
+A number literal is longer than the lexer's length limit.
+
+Number literals are limited to 1024 bytes by default; no integer Monkey
+can represent comes close. Check the input for a run of stray digits, or
+raise `max_number_len` in `LexerLimits`.",
+    ),
+    (
+        "L0004",
+        "\
+A string literal has no closing quote.
+
+Strings run from one `\"` to the next, and may span lines, so a missing
+quote swallows the rest of the file:
+
+    let greeting = \"hello;
+
+Add the closing quote:
+
+    let greeting = \"hello\";",
+    ),
+    (
+        "L0005",
+        "\
+A character which is not part of Monkey's syntax appeared outside a
+string or comment.
+
+Lookalikes pasted from a word processor are a common cause, such as curly
+quotes or a dash in place of a minus sign:
+
+    let greeting = “hello”;
+    let x = 5 – 3;
+
+Replace the character with the one Monkey expects, or remove it:
+
+    let greeting = \"hello\";
+    let x = 5 - 3;",
+    ),
+    (
+        "L0006",
+        "\
+Reading the input failed part-way through lexing it.
+
+This only happens when source is streamed from a reader rather than given
+as a string, and the reason is included in the message. Source which is
+not valid UTF-8 is the most common cause: Monkey source must be UTF-8.",
+    ),
+    (
+        "L0007",
+        "\
+A string literal is longer than the lexer's length limit.
+
+Strings are limited to 1 MiB by default, counting the text between the
+interpolations of a string separately. A string this long is usually one
+whose closing quote went missing much earlier:
+
+    let greeting = \"hello;
+    ... the rest of the file ...
+
+Close the string, or raise `max_string_len` in `LexerLimits`.",
+    ),
+    (
+        "P0001",
+        "\
+The parser needed a particular token and found a different one.
+
+This is usually a missing bracket or operator:
+
+    let x 5;           // missing `=`
+    (1 + 2;            // missing `)`
+    if (x) y           // missing `{` around the consequence
+
+Insert the expected token at the position reported:
+
+    let x = 5;
+    (1 + 2);
+    if (x) { y }",
+    ),
+    (
+        "P0002",
+        "\
+The parser needed a name and found something else.
+
+`let`, `const` and `catch` must be followed by the name being bound:
+
+    let = 5;
+    let 5 = x;
+    try { f } catch () { 0 }
+
+Keywords such as `if` or `fn` can't be used as names either. Add or rename
+the binding:
+
+    let x = 5;
+    try { f } catch (e) { 0 }",
+    ),
+    (
+        "P0003",
+        "\
+A token appeared where an expression should start, but no expression can
+start with it.
+
+This often follows another error, or comes from a stray or doubled
+operator:
+
+    let x = ;
+    1 + * 2
+    )
+
+Remove the stray token, or supply the missing expression:
+
+    let x = 1;
+    1 + 2 * 3",
+    ),
+    (
+        "P0004",
+        "\
+An integer literal is too large to represent.
+
+Monkey integers are signed 64-bit values, so literals may be at most
+9223372036854775807:
+
+    let big = 99999999999999999999;
+
+Use a smaller value.",
+    ),
+    (
+        "P0005",
+        "\
+The parser has no handler for an expression starting with this token.
+
+This is reserved for tokens which are recognised but whose expressions
+aren't supported by this version of the parser.",
+    ),
+    (
+        "P0006",
+        "\
+Expressions or blocks are nested more deeply than the parser allows.
+
+To avoid overflowing the stack, the parser stops after 100 levels of
+nested parentheses, prefix operators or blocks, unless an embedder raises
+the limit with `ParserOptions::max_nesting_depth`:
+
+    ((((((((((((((((( ... 1 ... )))))))))))))))))
+
+Give intermediate results names with `let` to flatten the expression:
+
+    let inner = (1 + 2) * 3;
+    let outer = inner - 4;",
+    ),
+    (
+        "P0007",
+        "\
+The program uses syntax which the parser was told not to accept.
+
+Embedders can lock scripts to a stricter dialect through `ParserOptions`,
+for instance turning off every extension, such as ranges, to accept only
+the language as described in the book:
+
+    let evens = filter(0..10, is_even);
+
+Write the expression without the extension, or parse the script with the
+option turned back on.",
+    ),
+    (
+        "W0001",
+        "\
+A statement computes a value which is then thrown away, and can't do
+anything else.
+
+Only the last statement in a block or program gives its value, so earlier
+expression statements without a call in them are dead code:
+
+    let total = fn(a, b) {
+        a + b;
+        a * b
+    };
+
+This is usually a leftover, or a missing `let` or `return`:
+
+    let total = fn(a, b) {
+        return a + b;
+    };",
+    ),
+    (
+        "W0002",
+        "\
+An `if` or `try` statement is followed by a semicolon.
+
+A statement ending in a block needs no semicolon after it:
+
+    if (ready) { start() };
+
+Remove the semicolon:
+
+    if (ready) { start() }",
+    ),
+];
+
+/// The explanation for `code`, ignoring case.
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(code))
+        .map(|(_, explanation)| *explanation)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lexer::LexerError;
+    use crate::parser::{Extension, ParserError, ParserWarning};
+    use crate::token::{Token, TokenKind};
+
+    #[test]
+    fn test_every_error_is_explained() {
+        let errors = [
+            ParserError::ExpectedToken {
+                expected: TokenKind::RParen,
+                saw: Token::from(TokenKind::EOF),
+            },
+            ParserError::ExpectedIdent(Token::from(TokenKind::Assign)),
+            ParserError::UnhandledPrefix(Token::from(TokenKind::Assign)),
+            ParserError::IntegerParseFailure("1".to_owned()),
+            ParserError::UnhandledExpression(Token::from(TokenKind::Assign)),
+            ParserError::NestingTooDeep(1),
+            ParserError::Disabled(Extension::Pipes),
+            ParserError::Lexer(LexerError::InputTooLarge { len: 2, max: 1 }),
+            ParserError::Lexer(LexerError::IdentifierTooLong { max: 1 }),
+            ParserError::Lexer(LexerError::NumberTooLong { max: 1 }),
+            ParserError::Lexer(LexerError::StringTooLong { max: 1 }),
+            ParserError::Lexer(LexerError::UnterminatedString),
+            ParserError::Lexer(LexerError::IllegalCharacter('@')),
+            ParserError::Lexer(LexerError::Read(
+                "stream did not contain valid UTF-8".to_owned(),
+            )),
+        ];
+        let mut codes: Vec<&str> = errors.iter().map(ParserError::code).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len(), "codes must be unique");
+        for code in codes {
+            assert!(explain(code).is_some(), "no explanation for {}", code);
+        }
+        for warning in [ParserWarning::NoEffect, ParserWarning::RedundantSemicolon] {
+            assert!(explain(warning.code()).is_some());
+        }
+    }
+
+    #[test]
+    fn test_explain() {
+        assert!(explain("P0003").unwrap().contains("no expression can"));
+        assert_eq!(explain("p0003"), explain("P0003"));
+        assert_eq!(explain("P9999"), None);
+    }
+}
@@ -0,0 +1,9 @@
+/// Opt-in language extensions. Everything is off by default, which is the
+/// language as described in the book.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LanguageFeatures {
+    /// Accept keywords in any letter case, e.g. `True`, `FN` or `Let`. They
+    /// lex to the usual keyword tokens, so printing the AST normalises them to
+    /// lower case.
+    pub case_insensitive_keywords: bool,
+}
@@ -0,0 +1,503 @@
+//! Re-parsing a source file after an edit, for editors which re-check the
+//! file on every keystroke.
+//!
+//! Top-level statements an edit can't have changed are reused as they are,
+//! and only the text from the statement before the edit to the first
+//! statement boundary after it is lexed and parsed again. A boundary is
+//! where one top-level statement ends and the parser has read the two
+//! tokens after it; if those tokens are in the same place before and after
+//! the edit, everything after them parses the same way too.
+
+use crate::ast::{BlockStatement, Expression, Pattern, Program, Statement, StringPart};
+use crate::lexer::{Lexer, LexerError, LexerLimits};
+use crate::parser::{Parser, ParserError};
+use crate::span::Span;
+
+/// A change to source text: the bytes in `span` are replaced by `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(span: Span, text: &str) -> Self {
+        TextEdit {
+            span,
+            text: text.to_owned(),
+        }
+    }
+
+    /// `source` with the edit made.
+    pub fn apply(&self, source: &str) -> String {
+        let mut edited = source.to_owned();
+        edited.replace_range(self.span.start..self.span.end, &self.text);
+        edited
+    }
+
+    /// How far text after the edit moves.
+    fn shift(&self) -> isize {
+        self.text.len() as isize - self.span.len() as isize
+    }
+}
+
+/// A parsed source file, with what `reparse` needs to re-parse it.
+#[derive(Debug)]
+pub struct ParsedSource {
+    pub program: Program,
+    /// Each parser error and the span it was reported at.
+    pub errors: Vec<(ParserError, Span)>,
+    /// Where each of the program's statements ended.
+    boundaries: Vec<Boundary>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Boundary {
+    /// The statement, from its first token to its last.
+    span: Span,
+    /// The two tokens the parser had read after the statement.
+    next: Span,
+    peek: Span,
+    /// Whether the lexer was outside any string at that point.
+    clean: bool,
+}
+
+impl Boundary {
+    fn shifted(self, by: isize) -> Self {
+        Boundary {
+            span: shift(self.span, by),
+            next: shift(self.next, by),
+            peek: shift(self.peek, by),
+            clean: self.clean,
+        }
+    }
+}
+
+fn shift(span: Span, by: isize) -> Span {
+    Span::new(
+        (span.start as isize + by) as usize,
+        (span.end as isize + by) as usize,
+    )
+}
+
+/// Move every span in `stmt` by `by`, for a statement carried over from
+/// before an edit.
+fn shift_statement(stmt: &mut Statement, by: isize) {
+    let mut stmts = vec![stmt];
+    let mut exprs: Vec<&mut Expression> = vec![];
+    loop {
+        if let Some(expr) = exprs.pop() {
+            match expr {
+                Expression::Identifier(ident) => ident.1 = shift(ident.1, by),
+                Expression::IntegerLiteral(_)
+                | Expression::StringLiteral(_)
+                | Expression::Boolean(_)
+                | Expression::Nothing => {}
+                Expression::Interpolated(parts) => {
+                    for part in parts {
+                        if let StringPart::Expr(expr) = part {
+                            exprs.push(expr);
+                        }
+                    }
+                }
+                Expression::ArrayLiteral(elements) => exprs.extend(elements),
+                Expression::Prefix { operator, right } => {
+                    operator.span = shift(operator.span, by);
+                    exprs.push(right);
+                }
+                Expression::Infix {
+                    left,
+                    operator,
+                    right,
+                } => {
+                    operator.span = shift(operator.span, by);
+                    exprs.push(left);
+                    exprs.push(right);
+                }
+                Expression::If {
+                    condition,
+                    consequence,
+                    alternative,
+                } => {
+                    exprs.push(condition);
+                    shift_block(consequence, by, &mut stmts);
+                    if let Some(alternative) = alternative {
+                        shift_block(alternative, by, &mut stmts);
+                    }
+                }
+                Expression::Try {
+                    body,
+                    binding,
+                    handler,
+                } => {
+                    binding.1 = shift(binding.1, by);
+                    shift_block(body, by, &mut stmts);
+                    shift_block(handler, by, &mut stmts);
+                }
+                Expression::Function {
+                    parameters, body, ..
+                } => {
+                    for parameter in parameters {
+                        parameter.1 = shift(parameter.1, by);
+                    }
+                    shift_block(body, by, &mut stmts);
+                }
+                Expression::Range { start, end, .. } => {
+                    exprs.push(start);
+                    exprs.push(end);
+                }
+                Expression::Call {
+                    function,
+                    arguments,
+                } => {
+                    exprs.push(function);
+                    exprs.extend(arguments);
+                }
+                Expression::Index { left, index } => {
+                    exprs.push(left);
+                    exprs.push(index);
+                }
+                Expression::Slice { left, start, end } => {
+                    exprs.push(left);
+                    exprs.extend(start.iter_mut().chain(end).map(|bound| &mut **bound));
+                }
+            }
+            continue;
+        }
+        let stmt = match stmts.pop() {
+            Some(stmt) => stmt,
+            None => return,
+        };
+        match stmt {
+            Statement::Let {
+                token, name, value, ..
+            } => {
+                token.span = shift(token.span, by);
+                name.1 = shift(name.1, by);
+                exprs.push(value);
+            }
+            Statement::Destructure {
+                token,
+                pattern,
+                value,
+            } => {
+                token.span = shift(token.span, by);
+                let mut patterns = vec![pattern];
+                while let Some(pattern) = patterns.pop() {
+                    match pattern {
+                        Pattern::Identifier(name) => name.1 = shift(name.1, by),
+                        Pattern::Array(elements) => patterns.extend(elements),
+                        Pattern::Hash(names) => {
+                            for name in names {
+                                name.1 = shift(name.1, by);
+                            }
+                        }
+                    }
+                }
+                exprs.push(value);
+            }
+            Statement::Return { token, expr } | Statement::Expression { token, expr } => {
+                token.span = shift(token.span, by);
+                exprs.push(expr);
+            }
+        }
+    }
+}
+
+fn shift_block<'a>(block: &'a mut BlockStatement, by: isize, stmts: &mut Vec<&'a mut Statement>) {
+    block.token.span = shift(block.token.span, by);
+    stmts.extend(&mut block.statements);
+}
+
+/// Parse `source` from the start.
+pub fn parse(source: &str) -> ParsedSource {
+    let mut parsed = ParsedSource {
+        program: Program::default(),
+        errors: vec![],
+        boundaries: vec![],
+    };
+    let mut parser = Parser::from_input(source);
+    parse_from(&mut parser, &mut parsed, |_| false);
+    parsed
+}
+
+/// Parse statements into `parsed` until the end of the input, or until
+/// `resync` returns true for the boundary after a statement. Returns
+/// whether it stopped at a boundary.
+fn parse_from(
+    parser: &mut Parser<Lexer>,
+    parsed: &mut ParsedSource,
+    mut resync: impl FnMut(&Boundary) -> bool,
+) -> bool {
+    let mut stopped = false;
+    while !parser.at_eof() {
+        if let Some((stmt, span)) = parser.parse_top_level_statement() {
+            let (next, peek) = parser.lookahead();
+            let boundary = Boundary {
+                span,
+                next,
+                peek,
+                clean: !parser.tokens().in_interpolation(),
+            };
+            parsed.program.statements.push(stmt);
+            parsed.boundaries.push(boundary);
+            if resync(&boundary) {
+                stopped = true;
+                break;
+            }
+        }
+    }
+    if !stopped {
+        parser.finish();
+    }
+    let errors = parser.errors().iter().cloned();
+    parsed
+        .errors
+        .extend(errors.zip(parser.error_spans().iter().copied()));
+    stopped
+}
+
+/// Re-parse `source`, which is the text `old` was parsed from with `edit`
+/// made to it. The result is the same as `parse(source)`.
+pub fn reparse(old: ParsedSource, source: &str, edit: &TextEdit) -> ParsedSource {
+    // An error which stopped the lexer may have hidden anything after it,
+    // and only a whole input can be checked against the size limit.
+    let stopped_early = old.errors.iter().any(|(err, _)| {
+        matches!(err, ParserError::Lexer(err) if !matches!(err, LexerError::IllegalCharacter(_)))
+    });
+    if stopped_early || source.len() > LexerLimits::default().max_input_len {
+        return parse(source);
+    }
+
+    // Statements before the edit, and the tokens read after them, can't
+    // have changed. The next token must end before the edit, rather than
+    // at its start, in case inserted text would join on to it.
+    let kept = old
+        .boundaries
+        .iter()
+        .take_while(|boundary| boundary.clean && boundary.peek.end < edit.span.start)
+        .count();
+    let start = match kept {
+        0 => 0,
+        kept => old.boundaries[kept - 1].span.end,
+    };
+
+    let ParsedSource {
+        program,
+        errors,
+        boundaries,
+    } = old;
+    let shift = edit.shift();
+    let edit_end = edit.span.start + edit.text.len();
+    let mut old_statements = program.statements.into_iter();
+    let mut parsed = ParsedSource {
+        program: Program {
+            statements: old_statements.by_ref().take(kept).collect(),
+        },
+        errors: errors
+            .iter()
+            .filter(|(_, span)| span.start < start)
+            .cloned()
+            .collect(),
+        boundaries: boundaries[..kept].to_vec(),
+    };
+
+    // Parse until a statement ending after the edit is followed by the
+    // same tokens as before it. The text from there on hasn't changed, so
+    // neither has the doc comment of the statement after it.
+    let mut resynced = None;
+    let mut parser = Parser::new(Lexer::starting_at(source, start));
+    let stopped = parse_from(&mut parser, &mut parsed, |boundary| {
+        if !boundary.clean || boundary.span.end < edit_end {
+            return false;
+        }
+        // The statement itself may hold the edit, so only its end is
+        // compared.
+        let before = boundary.shifted(-shift);
+        resynced = boundaries[kept..]
+            .binary_search_by_key(&before.span.end, |old| old.span.end)
+            .ok()
+            .map(|idx| kept + idx)
+            .filter(|&idx| {
+                let old = boundaries[idx];
+                old.clean && old.next == before.next && old.peek == before.peek
+            });
+        resynced.is_some()
+    });
+    if !stopped {
+        return parsed;
+    }
+    let resynced = resynced.expect("stopped at a matching boundary");
+
+    // Everything after the matching boundary carries over, moved by the
+    // edit. Illegal characters before its peek token were reported while
+    // parsing up to the boundary, so only later ones are kept.
+    let Boundary { next, peek, .. } = boundaries[resynced];
+    let statements = old_statements.skip(resynced + 1 - kept).map(|mut stmt| {
+        shift_statement(&mut stmt, shift);
+        stmt
+    });
+    parsed.program.statements.extend(statements);
+    parsed.boundaries.extend(
+        boundaries[resynced + 1..]
+            .iter()
+            .map(|boundary| boundary.shifted(shift)),
+    );
+    parsed.errors.extend(
+        errors
+            .into_iter()
+            .filter(|(err, span)| {
+                span.start >= peek.start
+                    || span.start >= next.start
+                        && !matches!(err, ParserError::Lexer(LexerError::IllegalCharacter(_)))
+            })
+            .map(|(err, span)| (err, self::shift(span, shift))),
+    );
+    parsed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lint::lint;
+    use crate::token::{Token, TokenKind};
+    use proptest::prelude::*;
+
+    fn reparsed(source: &str, edit: TextEdit) -> (ParsedSource, ParsedSource) {
+        let edited = edit.apply(source);
+        let incremental = reparse(parse(source), &edited, &edit);
+        (incremental, parse(&edited))
+    }
+
+    fn assert_same(incremental: &ParsedSource, full: &ParsedSource) {
+        assert_eq!(incremental.program.statements, full.program.statements);
+        assert_eq!(
+            format!("{:?}", incremental.errors),
+            format!("{:?}", full.errors)
+        );
+        assert_eq!(incremental.boundaries, full.boundaries);
+        // Statements compare equal wherever they are, but the spans lints
+        // are reported at must have moved with the edit too.
+        assert_eq!(lint(&incremental.program), lint(&full.program));
+    }
+
+    #[test]
+    fn test_text_edit_apply() {
+        let edit = TextEdit::new(Span::new(8, 9), "10");
+        assert_eq!(edit.apply("let a = 1;"), "let a = 10;");
+    }
+
+    #[test]
+    fn test_reparse_reuses_statements_around_edit() {
+        let source = "let a = 1;\nlet b = 2;\nlet c = 3;\nlet d = 4;\n";
+        let (incremental, full) = reparsed(source, TextEdit::new(Span::new(19, 20), "a + 20"));
+        assert_same(&incremental, &full);
+        assert_eq!(
+            incremental.program.to_string(),
+            "let a = 1;\nlet b = (a + 20);\nlet c = 3;\nlet d = 4;\n"
+        );
+    }
+
+    #[test]
+    fn test_reparse_keeps_old_statements() {
+        // Swap in statements the source doesn't hold, to see which of them
+        // survive the re-parse.
+        let source = "let a = 1;\nlet b = 2;\nlet c = 3;\nlet d = 4;\n";
+        let mut old = parse(source);
+        for stmt in &mut old.program.statements {
+            *stmt = Statement::Expression {
+                token: Token::from(TokenKind::True),
+                expr: Expression::Boolean(true),
+            };
+        }
+        let edit = TextEdit::new(Span::new(19, 20), "20");
+        let reparsed = reparse(old, &edit.apply(source), &edit);
+        assert_eq!(
+            reparsed.program.to_string(),
+            "true;\nlet b = 20;\ntrue;\ntrue\n"
+        );
+    }
+
+    #[test]
+    fn test_reparse_edit_spanning_statements() {
+        let source = "let a = 1;\nlet b = [2,\n3];\nlet c = 4;\n";
+        let (incremental, full) = reparsed(source, TextEdit::new(Span::new(21, 22), ""));
+        assert_same(&incremental, &full);
+        assert_eq!(incremental.program.statements.len(), 3);
+    }
+
+    #[test]
+    fn test_reparse_introducing_and_fixing_errors() {
+        let source = "let a = 1;\nlet b = 2;\nlet c = 3;\n";
+        let (broken, full) = reparsed(source, TextEdit::new(Span::new(19, 20), "@"));
+        assert_same(&broken, &full);
+        assert!(!broken.errors.is_empty());
+
+        let source = TextEdit::new(Span::new(19, 20), "@").apply(source);
+        let edit = TextEdit::new(Span::new(19, 20), "2");
+        let fixed = reparse(broken, &edit.apply(&source), &edit);
+        assert!(fixed.errors.is_empty(), "{:?}", fixed.errors);
+        assert_eq!(fixed.program.statements.len(), 3);
+    }
+
+    #[test]
+    fn test_reparse_opening_string() {
+        let source = "let a = 1;\nlet b = 2;\nlet c = 3;\n";
+        let (incremental, full) = reparsed(source, TextEdit::new(Span::new(19, 19), "\""));
+        assert_same(&incremental, &full);
+    }
+
+    #[test]
+    fn test_reparse_doc_comment() {
+        let source = "let a = 1;\n/// Old.\nlet b = 2;\nlet c = 3;\n";
+        let (incremental, full) = reparsed(source, TextEdit::new(Span::new(15, 18), "New"));
+        assert_same(&incremental, &full);
+    }
+
+    #[test]
+    fn test_reparse_after_shebang() {
+        let source = "#!/usr/bin/env monkey\nlet a = 5;\nlet b = a;\nb\n";
+        let (incremental, full) = reparsed(source, TextEdit::new(Span::new(30, 31), "6"));
+        assert_same(&incremental, &full);
+        assert!(incremental.errors.is_empty(), "{:?}", incremental.errors);
+        assert_eq!(
+            incremental.program.to_string(),
+            "let a = 6;\nlet b = a;\nb\n"
+        );
+    }
+
+    /// An edit replacing a char-aligned range of `source` with `text`.
+    fn edit_of(source: &str, (a, b): (usize, usize), text: String) -> TextEdit {
+        let bounds: Vec<usize> = source
+            .char_indices()
+            .map(|(idx, _)| idx)
+            .chain(Some(source.len()))
+            .collect();
+        let a = bounds[a % bounds.len()];
+        let b = bounds[b % bounds.len()];
+        TextEdit::new(Span::new(a.min(b), a.max(b)), &text)
+    }
+
+    proptest! {
+        #[test]
+        fn test_reparse_matches_parse(
+            program in any::<Program>(),
+            shebang in any::<bool>(),
+            range in (0..200usize, 0..200usize),
+            text in prop_oneof!["[a-z0-9 ;(){}\\[\\]\"$@\n/]{0,4}", "let x = [0-9]{1,2};\n"],
+        ) {
+            let mut source = program.to_string();
+            if shebang {
+                source.insert_str(0, "#!/usr/bin/env monkey\n");
+            }
+            let edit = edit_of(&source, range, text);
+            let (incremental, full) = reparsed(&source, edit);
+            prop_assert_eq!(&incremental.program.statements, &full.program.statements);
+            prop_assert_eq!(
+                format!("{:?}", incremental.errors),
+                format!("{:?}", full.errors)
+            );
+            prop_assert_eq!(&incremental.boundaries, &full.boundaries);
+        }
+    }
+}
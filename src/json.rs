@@ -0,0 +1,63 @@
+//! Minimal helpers for emitting JSON without pulling in a serialisation crate.
+
+/// Quote and escape a string as a JSON string literal.
+pub fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Render `(key, value)` pairs as a single-line JSON object. Values must
+/// already be valid JSON (e.g. produced by `quote`).
+pub fn object(fields: &[(&str, String)]) -> String {
+    let body: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{}:{}", quote(key), value))
+        .collect();
+    format!("{{{}}}", body.join(","))
+}
+
+/// Render already-encoded JSON values as a JSON array.
+pub fn array(values: &[String]) -> String {
+    format!("[{}]", values.join(","))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("abc"), r#""abc""#);
+        assert_eq!(quote("a\"b\\c"), r#""a\"b\\c""#);
+        assert_eq!(quote("line\n\t\u{1}"), r#""line\n\t\u0001""#);
+        assert_eq!(quote("café"), "\"café\"");
+    }
+
+    #[test]
+    fn test_array() {
+        assert_eq!(array(&[]), "[]");
+        assert_eq!(array(&[quote("a"), 2.to_string()]), r#"["a",2]"#);
+    }
+
+    #[test]
+    fn test_object() {
+        assert_eq!(object(&[]), "{}");
+        assert_eq!(
+            object(&[("type", quote("token")), ("n", 1.to_string())]),
+            r#"{"type":"token","n":1}"#
+        );
+    }
+}
@@ -1,52 +1,408 @@
-use crate::token::Token;
-use std::iter::Peekable;
-use std::str::Chars;
+use crate::features::LanguageFeatures;
+use crate::span::Span;
+use crate::token::{Token, TokenKind};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::io::BufRead;
+use unicode_ident::{is_xid_continue, is_xid_start};
 
 pub fn lookup_ident(ident: &str) -> Token {
-    match ident {
-        "let" => Token::Let,
-        "fn" => Token::Function,
-        "if" => Token::If,
-        "else" => Token::Else,
-        "return" => Token::Return,
-        "true" => Token::True,
-        "false" => Token::False,
-        _ => Token::Ident(ident.to_string()),
+    Token::lookup_ident(ident)
+}
+
+/// Upper bounds on the size of the input and of individual literals, in
+/// bytes. Exceeding any of them is fatal: the lexer records a `LexerError`
+/// and reports `EOF` from then on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LexerLimits {
+    pub max_input_len: usize,
+    pub max_identifier_len: usize,
+    pub max_number_len: usize,
+    /// Applies to each piece of text between the interpolations of a
+    /// string separately.
+    pub max_string_len: usize,
+}
+
+impl LexerLimits {
+    /// Limits which never trigger.
+    pub fn unlimited() -> Self {
+        LexerLimits {
+            max_input_len: usize::MAX,
+            max_identifier_len: usize::MAX,
+            max_number_len: usize::MAX,
+            max_string_len: usize::MAX,
+        }
+    }
+}
+
+impl Default for LexerLimits {
+    fn default() -> Self {
+        LexerLimits {
+            max_input_len: 16 * 1024 * 1024,
+            max_identifier_len: 1024,
+            max_number_len: 1024,
+            max_string_len: 1024 * 1024,
+        }
+    }
+}
+
+/// Extra words recognised on top of the built-in keywords, for dialects of
+/// the language. A word may stand for a sequence of tokens, e.g. `elsif` for
+/// `else if`. Entries take precedence over the built-in keywords.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Keywords {
+    words: HashMap<String, Vec<Token>>,
+}
+
+impl Keywords {
+    pub fn new() -> Self {
+        Keywords::default()
+    }
+
+    /// Lex `word` as `token`.
+    pub fn alias(self, word: &str, token: Token) -> Self {
+        self.alias_sequence(word, vec![token])
+    }
+
+    /// Lex `word` as each of `tokens` in turn.
+    pub fn alias_sequence(mut self, word: &str, tokens: Vec<Token>) -> Self {
+        self.words.insert(word.to_owned(), tokens);
+        self
+    }
+
+    pub fn lookup(&self, word: &str) -> Option<&[Token]> {
+        self.words.get(word).map(Vec::as_slice)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexerError {
+    InputTooLarge {
+        len: usize,
+        max: usize,
+    },
+    IdentifierTooLong {
+        max: usize,
+    },
+    NumberTooLong {
+        max: usize,
+    },
+    StringTooLong {
+        max: usize,
+    },
+    UnterminatedString,
+    IllegalCharacter(char),
+    /// Reading streamed input failed, with the reason.
+    Read(String),
+}
+
+impl LexerError {
+    /// The stable code identifying this kind of error, for `monkey explain`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexerError::InputTooLarge { .. } => "L0001",
+            LexerError::IdentifierTooLong { .. } => "L0002",
+            LexerError::NumberTooLong { .. } => "L0003",
+            LexerError::UnterminatedString => "L0004",
+            LexerError::IllegalCharacter(_) => "L0005",
+            LexerError::Read(_) => "L0006",
+            LexerError::StringTooLong { .. } => "L0007",
+        }
+    }
+
+    /// A hint at how to fix the error, where there is an obvious one.
+    pub fn suggestion(&self) -> Option<String> {
+        let hint = match self {
+            LexerError::IllegalCharacter(ch) => match ch {
+                '“' | '”' | '„' => "use straight quotes `\"` for strings",
+                '\'' | '‘' | '’' | '`' => "strings use double quotes `\"`",
+                '–' | '—' | '−' => "did you mean `-`?",
+                '×' => "did you mean `*`?",
+                '÷' => "did you mean `/`?",
+                '≠' => "did you mean `!=`?",
+                '\u{37e}' => "did you mean `;`? This is a Greek question mark",
+                '#' => "comments start with `//`",
+                '\u{200b}' | '\u{feff}' => "remove the invisible character",
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(hint.to_owned())
+    }
+}
+
+impl Display for LexerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexerError::InputTooLarge { len, max } => write!(
+                f,
+                "Input is {} bytes long, exceeding the limit of {} bytes",
+                len, max
+            ),
+            LexerError::IdentifierTooLong { max } => {
+                write!(f, "Identifier exceeds the limit of {} bytes", max)
+            }
+            LexerError::NumberTooLong { max } => {
+                write!(f, "Number literal exceeds the limit of {} bytes", max)
+            }
+            LexerError::StringTooLong { max } => {
+                write!(f, "String literal exceeds the limit of {} bytes", max)
+            }
+            LexerError::UnterminatedString => write!(f, "String literal is never closed"),
+            LexerError::IllegalCharacter(ch) => write!(f, "Illegal character {:?}", ch),
+            LexerError::Read(reason) => write!(f, "Could not read input: {}", reason),
+        }
+    }
+}
+
+/// Streamed input, read a line at a time as the lexer needs it.
+struct Stream<'a> {
+    reader: Box<dyn BufRead + 'a>,
+    /// Bytes read so far.
+    len: usize,
+}
+
+impl fmt::Debug for Stream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Stream").field("len", &self.len).finish()
     }
 }
 
 #[derive(Debug)]
 pub struct Lexer<'a> {
-    input: &'a str,
+    /// The input from byte `offset` onwards. Streamed input is owned, and
+    /// text before the current token is dropped as lexing goes.
+    input: Cow<'a, str>,
+    offset: usize,
+    stream: Option<Stream<'a>>,
     position: usize,
     read_position: usize,
-    iterator: Peekable<Chars<'a>>,
     ch: Option<char>,
+    limits: LexerLimits,
+    error: Option<LexerError>,
+    keywords: Keywords,
+    features: LanguageFeatures,
+    /// Remaining tokens of a multi-token keyword, in reverse order, and the
+    /// span of the word they came from.
+    pending: Vec<Token>,
+    pending_span: Span,
+    /// Lines of the `///` comments directly before the last token lexed.
+    doc: Vec<String>,
+    /// For each `${` being lexed, innermost last, the number of braces
+    /// opened inside it and not yet closed.
+    interpolations: Vec<usize>,
+    /// The error for the last token lexed, if it was `Illegal`.
+    illegal: Option<LexerError>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
-        let iterator = input.chars().peekable();
-        let mut lexer = Lexer {
+        Lexer::with_limits(input, LexerLimits::default())
+    }
+
+    pub fn with_limits(input: &'a str, limits: LexerLimits) -> Self {
+        let mut lexer = Lexer::with_input(Cow::Borrowed(input), None, limits);
+        if input.len() > limits.max_input_len {
+            lexer.error = Some(LexerError::InputTooLarge {
+                len: input.len(),
+                max: limits.max_input_len,
+            });
+        } else {
+            lexer.read_char();
+            lexer.skip_shebang();
+        }
+        lexer
+    }
+
+    /// Lex `input` from byte `offset` on, which must start a line or follow
+    /// a complete token. Spans are offsets into the whole of `input`. From
+    /// offset 0 a shebang line is skipped, as `Lexer::new` does.
+    pub(crate) fn starting_at(input: &'a str, offset: usize) -> Self {
+        let mut lexer = Lexer::with_input(Cow::Borrowed(input), None, LexerLimits::default());
+        lexer.read_position = offset;
+        lexer.read_char();
+        if offset == 0 {
+            lexer.skip_shebang();
+        }
+        lexer
+    }
+
+    /// Lex from `reader`, holding only the current line or token in memory
+    /// rather than the whole input. Spans are still byte offsets from the
+    /// start of the input.
+    pub fn from_reader<R: BufRead + 'a>(reader: R) -> Self {
+        Lexer::from_reader_with_limits(reader, LexerLimits::default())
+    }
+
+    pub fn from_reader_with_limits<R: BufRead + 'a>(reader: R, limits: LexerLimits) -> Self {
+        let stream = Stream {
+            reader: Box::new(reader),
+            len: 0,
+        };
+        let mut lexer = Lexer::with_input(Cow::Owned(String::new()), Some(stream), limits);
+        lexer.fill();
+        lexer.read_char();
+        lexer.skip_shebang();
+        lexer
+    }
+
+    fn with_input(input: Cow<'a, str>, stream: Option<Stream<'a>>, limits: LexerLimits) -> Self {
+        Lexer {
             input,
+            offset: 0,
+            stream,
             position: 0,
             read_position: 0,
             ch: None,
-            iterator,
-        };
-        lexer.read_char();
-        lexer
+            limits,
+            error: None,
+            keywords: Keywords::default(),
+            features: LanguageFeatures::default(),
+            pending: vec![],
+            pending_span: Span::default(),
+            doc: vec![],
+            interpolations: vec![],
+            illegal: None,
+        }
+    }
+
+    /// Recognise the words in `keywords` in addition to the built-in ones.
+    pub fn with_keywords(mut self, keywords: Keywords) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    /// Enable the lexer-level parts of `features`.
+    pub fn with_features(mut self, features: LanguageFeatures) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Resolve an identifier against the dialect and built-in keywords.
+    fn lookup_word(&mut self, word: &str, span: Span) -> Token {
+        match self.keywords.lookup(word) {
+            Some([first, rest @ ..]) => {
+                self.pending = rest.iter().rev().cloned().collect();
+                self.pending_span = span;
+                first.clone()
+            }
+            _ if self.features.case_insensitive_keywords
+                && word.chars().any(char::is_uppercase) =>
+            {
+                match TokenKind::lookup_keyword(&word.to_lowercase()) {
+                    Some(keyword) => Token::from(keyword),
+                    None => Token::make_ident(word),
+                }
+            }
+            _ => Token::lookup_ident(word),
+        }
+    }
+
+    /// Take the text of the `///` doc comments directly before the last
+    /// token lexed, one line per comment with the first space after `///`
+    /// removed.
+    pub fn take_doc(&mut self) -> Option<String> {
+        if self.doc.is_empty() {
+            return None;
+        }
+        Some(std::mem::take(&mut self.doc).join("\n"))
+    }
+
+    /// Take the error for the last token lexed, if it was `Illegal`. Lexing
+    /// carries on past an illegal character, so unlike `error()` this does
+    /// not mean the lexer has stopped.
+    pub fn take_illegal(&mut self) -> Option<LexerError> {
+        self.illegal.take()
+    }
+
+    /// The error which stopped the lexer, if any.
+    pub fn error(&self) -> Option<&LexerError> {
+        self.error.as_ref()
+    }
+
+    /// Check whether the last token lexed was inside a `${...}`.
+    pub(crate) fn in_interpolation(&self) -> bool {
+        !self.interpolations.is_empty()
+    }
+
+    /// Record a fatal error, returning the `EOF` token reported from now on.
+    /// A read error which has already stopped the input is kept, as it is
+    /// the cause of whatever went wrong next.
+    fn fail(&mut self, error: LexerError) -> Token {
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+        self.ch = None;
+        Token::from(TokenKind::EOF)
     }
 
     /// Read a character and advance the read position.
     fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.ch = None;
-        } else {
-            self.ch = self.iterator.next();
-        }
+        self.ch = self.char_at(self.read_position);
         self.position = self.read_position;
-        self.read_position += 1;
+        if let Some(ch) = self.ch {
+            self.read_position += ch.len_utf8();
+            self.fill();
+        }
+    }
+
+    fn char_at(&self, position: usize) -> Option<char> {
+        self.input[position - self.offset..].chars().next()
+    }
+
+    /// The input between two byte offsets, which must not be before the
+    /// start of the current token.
+    fn slice(&self, start: usize, end: usize) -> &str {
+        &self.input[start - self.offset..end - self.offset]
+    }
+
+    /// Make sure streamed input holds the character after the current one,
+    /// unless the stream has ended.
+    fn fill(&mut self) {
+        let stream = match &mut self.stream {
+            Some(stream) => stream,
+            None => return,
+        };
+        let buffer = self.input.to_mut();
+        while self.read_position - self.offset >= buffer.len() {
+            match stream.reader.read_line(buffer) {
+                Ok(0) => {
+                    self.stream = None;
+                    return;
+                }
+                Ok(len) => {
+                    stream.len += len;
+                    if stream.len > self.limits.max_input_len {
+                        self.error = Some(LexerError::InputTooLarge {
+                            len: stream.len,
+                            max: self.limits.max_input_len,
+                        });
+                        self.stream = None;
+                        return;
+                    }
+                }
+                Err(err) => {
+                    self.error = Some(LexerError::Read(err.to_string()));
+                    self.stream = None;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Drop streamed input before the current character, which no token
+    /// still being lexed can refer to. This waits until at least as much
+    /// has been read as is left, so each byte is moved once on average
+    /// however long its line.
+    fn discard(&mut self) {
+        if let Cow::Owned(buffer) = &mut self.input {
+            let consumed = self.position - self.offset;
+            if consumed >= buffer.len() - consumed {
+                buffer.drain(..consumed);
+                self.offset = self.position;
+            }
+        }
     }
 
     fn skip_whitespace(&mut self) {
@@ -58,94 +414,402 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn read_identifier(&mut self) -> &str {
+    /// Skip a `#!` line at the very start of the input, so scripts can be
+    /// made executable. The newline is left, so spans are unchanged.
+    fn skip_shebang(&mut self) {
+        if self.ch != Some('#') || self.peek_char() != Some('!') {
+            return;
+        }
+        while !matches!(self.ch, Some('\n') | None) {
+            self.read_char();
+        }
+    }
+
+    /// Skip whitespace and `//` comments, collecting any doc comments
+    /// along the way.
+    fn skip_trivia(&mut self) {
+        self.doc.clear();
+        loop {
+            self.skip_whitespace();
+            if self.ch != Some('/') || self.peek_char() != Some('/') {
+                return;
+            }
+            let start = self.position;
+            while let Some(ch) = self.ch {
+                if ch == '\n' {
+                    break;
+                }
+                self.read_char();
+            }
+            let comment =
+                self.input[start - self.offset..self.position - self.offset].trim_end_matches('\r');
+            // `////...` is an ordinary comment, as in Rust.
+            if let Some(text) = comment.strip_prefix("///") {
+                if !text.starts_with('/') {
+                    let text = text.strip_prefix(' ').unwrap_or(text);
+                    self.doc.push(text.to_owned());
+                }
+            }
+        }
+    }
+
+    fn read_identifier(&mut self) -> Result<&str, LexerError> {
         let position = self.position;
 
         while let Some(ch) = self.ch {
-            if ch.is_alphabetic() || ch == '_' {
+            if is_xid_continue(ch) {
+                if self.position - position >= self.limits.max_identifier_len {
+                    return Err(LexerError::IdentifierTooLong {
+                        max: self.limits.max_identifier_len,
+                    });
+                }
                 self.read_char();
             } else {
                 break;
             }
         }
 
-        &self.input[position..self.position]
+        Ok(self.slice(position, self.position))
     }
 
-    fn read_number(&mut self) -> &str {
+    fn read_number(&mut self) -> Result<&str, LexerError> {
         let position = self.position;
 
         fn is_numeric(ch: Option<char>) -> bool {
             if let Some(ch) = ch {
-                ch.is_digit(10)
+                ch.is_ascii_digit()
             } else {
                 false
             }
         }
 
         while is_numeric(self.ch) {
+            if self.position - position >= self.limits.max_number_len {
+                return Err(LexerError::NumberTooLong {
+                    max: self.limits.max_number_len,
+                });
+            }
             self.read_char();
         }
 
-        &self.input[position..self.position]
+        Ok(self.slice(position, self.position))
+    }
+
+    /// Read string text after the opening `"`, or after the `}` closing an
+    /// interpolation, up to and past the closing `"` or the next `${`.
+    /// Returns the text and whether the string was closed.
+    fn read_string(&mut self) -> Result<(String, bool), LexerError> {
+        self.read_char();
+        let position = self.position;
+        let closed = loop {
+            match self.ch {
+                Some('"') => break true,
+                Some('$') if self.peek_char() == Some('{') => break false,
+                Some(_) => self.read_string_char(position)?,
+                None => return Err(LexerError::UnterminatedString),
+            }
+        };
+        let string = self.slice(position, self.position).to_owned();
+        self.read_char();
+        if !closed {
+            self.read_char();
+            self.interpolations.push(0);
+        }
+        Ok((string, closed))
     }
 
-    fn peek_char(&mut self) -> Option<&char> {
-        self.iterator.peek()
+    /// Read a raw string after the `r`, up to and past the closing `"`.
+    /// Raw strings run to the first `"` and never interpolate.
+    fn read_raw_string(&mut self) -> Result<String, LexerError> {
+        self.read_char();
+        self.read_char();
+        let position = self.position;
+        loop {
+            match self.ch {
+                Some('"') => break,
+                Some(_) => self.read_string_char(position)?,
+                None => return Err(LexerError::UnterminatedString),
+            }
+        }
+        let string = self.slice(position, self.position).to_owned();
+        self.read_char();
+        Ok(string)
     }
 
+    /// Read the next character of string text which began at `start`.
+    fn read_string_char(&mut self, start: usize) -> Result<(), LexerError> {
+        if self.position - start >= self.limits.max_string_len {
+            return Err(LexerError::StringTooLong {
+                max: self.limits.max_string_len,
+            });
+        }
+        self.read_char();
+        Ok(())
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.char_at(self.read_position)
+    }
+
+    /// Lex the next token. `EOF` has an empty span at the end of the input.
     pub fn next_token(&mut self) -> Token {
-        self.skip_whitespace();
-        let tok = match self.ch {
+        self.illegal = None;
+        if let Some(tok) = self.pending.pop() {
+            return tok.at(self.pending_span);
+        }
+        if self.error.is_some() {
+            return Token::from(TokenKind::EOF).at(Span::new(self.position, self.position));
+        }
+        self.discard();
+        self.skip_trivia();
+        let start = self.position;
+        let kind = match self.ch {
             Some('=') => {
                 if let Some('=') = self.peek_char() {
                     self.read_char();
-                    Token::Eq
+                    TokenKind::Eq
                 } else {
-                    Token::Assign
+                    TokenKind::Assign
                 }
             }
-            Some('+') => Token::Plus,
-            Some('-') => Token::Minus,
+            Some('+') => TokenKind::Plus,
+            Some('-') => TokenKind::Minus,
             Some('!') => {
                 if let Some('=') = self.peek_char() {
                     self.read_char();
-                    Token::NotEq
+                    TokenKind::NotEq
                 } else {
-                    Token::Bang
+                    TokenKind::Bang
                 }
             }
-            Some('*') => Token::Asterisk,
-            Some('/') => Token::Slash,
+            Some('*') => {
+                if let Some('*') = self.peek_char() {
+                    self.read_char();
+                    TokenKind::Power
+                } else {
+                    TokenKind::Asterisk
+                }
+            }
+            Some('/') => TokenKind::Slash,
 
-            Some('<') => Token::Lt,
-            Some('>') => Token::Gt,
+            Some('<') => {
+                if let Some('<') = self.peek_char() {
+                    self.read_char();
+                    TokenKind::ShiftLeft
+                } else {
+                    TokenKind::Lt
+                }
+            }
+            Some('>') => {
+                if let Some('>') = self.peek_char() {
+                    self.read_char();
+                    TokenKind::ShiftRight
+                } else {
+                    TokenKind::Gt
+                }
+            }
+            Some('&') => TokenKind::Ampersand,
+            Some('^') => TokenKind::Caret,
+            Some('~') => TokenKind::Tilde,
+            Some('|') => {
+                if let Some('>') = self.peek_char() {
+                    self.read_char();
+                    TokenKind::Pipe
+                } else {
+                    TokenKind::Bar
+                }
+            }
 
-            Some('(') => Token::LParen,
-            Some(')') => Token::RParen,
-            Some('{') => Token::LBrace,
-            Some('}') => Token::RBrace,
-            Some(',') => Token::Comma,
-            Some(';') => Token::Semicolon,
-            Some(ch) => {
-                if ch.is_alphabetic() {
-                    return Token::lookup_ident(self.read_identifier());
-                } else if ch.is_digit(10) {
-                    return Token::make_int(self.read_number());
+            Some('(') => TokenKind::LParen,
+            Some(')') => TokenKind::RParen,
+            Some('{') => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                TokenKind::LBrace
+            }
+            Some('}') if self.interpolations.last() == Some(&0) => {
+                self.interpolations.pop();
+                let tok = match self.read_string() {
+                    Ok((string, true)) => Token::new(TokenKind::InterpolationEnd, string),
+                    Ok((string, false)) => Token::new(TokenKind::InterpolationMid, string),
+                    Err(err) => self.fail(err),
+                };
+                return tok.at(Span::new(start, self.position));
+            }
+            Some('}') => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth -= 1;
+                }
+                TokenKind::RBrace
+            }
+            Some('[') => TokenKind::LBracket,
+            Some(']') => TokenKind::RBracket,
+            Some(',') => TokenKind::Comma,
+            Some(';') => TokenKind::Semicolon,
+            Some(':') => TokenKind::Colon,
+            Some('.') => {
+                if let Some('.') = self.peek_char() {
+                    self.read_char();
+                    if let Some('=') = self.peek_char() {
+                        self.read_char();
+                        TokenKind::DotDotEq
+                    } else {
+                        TokenKind::DotDot
+                    }
                 } else {
-                    return Token::Illegal;
+                    TokenKind::Dot
                 }
             }
-            None => Token::EOF,
+            Some('"') => {
+                let tok = match self.read_string() {
+                    Ok((string, true)) => Token::new(TokenKind::String, string),
+                    Ok((string, false)) => Token::new(TokenKind::InterpolationStart, string),
+                    Err(err) => self.fail(err),
+                };
+                return tok.at(Span::new(start, self.position));
+            }
+            Some('r') if self.peek_char() == Some('"') => {
+                let tok = match self.read_raw_string() {
+                    Ok(string) => Token::new(TokenKind::String, string),
+                    Err(err) => self.fail(err),
+                };
+                return tok.at(Span::new(start, self.position));
+            }
+            Some(ch) if is_xid_start(ch) || ch == '_' => {
+                let tok = match self.read_identifier() {
+                    Ok(ident) => {
+                        let ident = ident.to_owned();
+                        self.lookup_word(&ident, Span::new(start, self.position))
+                    }
+                    Err(err) => self.fail(err),
+                };
+                return tok.at(Span::new(start, self.position));
+            }
+            Some(ch) if ch.is_ascii_digit() => {
+                let tok = match self.read_number() {
+                    Ok(number) => Token::make_int(number),
+                    Err(err) => self.fail(err),
+                };
+                return tok.at(Span::new(start, self.position));
+            }
+            Some(ch) => {
+                self.illegal = Some(LexerError::IllegalCharacter(ch));
+                self.read_char();
+                let tok = Token::new(TokenKind::Illegal, ch.to_string());
+                return tok.at(Span::new(start, self.position));
+            }
+            // Input ending inside `${ ... }` leaves the string unclosed.
+            None if !self.interpolations.is_empty() => {
+                let tok = self.fail(LexerError::UnterminatedString);
+                return tok.at(Span::new(start, start));
+            }
+            None => return Token::from(TokenKind::EOF).at(Span::new(start, start)),
         };
         self.read_char();
-        tok
+        Token::from(kind).at(Span::new(start, self.position))
+    }
+}
+
+/// A source of tokens for the parser, each with the span it was read from.
+/// After the last token, `EOF` is returned from then on.
+pub trait TokenStream {
+    fn next_token(&mut self) -> Token;
+
+    /// Take the doc comment directly before the last token.
+    fn take_doc(&mut self) -> Option<String> {
+        None
+    }
+
+    /// Take the error for the last token, if it was `Illegal`.
+    fn take_illegal(&mut self) -> Option<LexerError> {
+        None
+    }
+
+    /// The error which ended the stream early, if any.
+    fn error(&self) -> Option<&LexerError> {
+        None
+    }
+}
+
+impl TokenStream for Lexer<'_> {
+    fn next_token(&mut self) -> Token {
+        Lexer::next_token(self)
+    }
+
+    fn take_doc(&mut self) -> Option<String> {
+        Lexer::take_doc(self)
+    }
+
+    fn take_illegal(&mut self) -> Option<LexerError> {
+        Lexer::take_illegal(self)
+    }
+
+    fn error(&self) -> Option<&LexerError> {
+        Lexer::error(self)
+    }
+}
+
+/// Tokens from an iterator of spanned tokens, such as hand-built ones in a
+/// test or those `lex_with_spans` returned earlier. `EOF` is given an
+/// empty span at the end of the last token.
+#[derive(Debug, Clone)]
+pub struct Tokens<I> {
+    iter: I,
+    end: usize,
+}
+
+impl<I: Iterator<Item = Token>> Tokens<I> {
+    pub fn new<T: IntoIterator<IntoIter = I>>(tokens: T) -> Self {
+        Tokens {
+            iter: tokens.into_iter(),
+            end: 0,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Token>> TokenStream for Tokens<I> {
+    fn next_token(&mut self) -> Token {
+        match self.iter.next() {
+            Some(tok) if tok != TokenKind::EOF => {
+                self.end = tok.span.end;
+                tok
+            }
+            _ => Token::from(TokenKind::EOF).at(Span::new(self.end, self.end)),
+        }
+    }
+}
+
+/// Lex `input` into tokens, each carrying the byte range it was read from,
+/// stopping before the final `EOF`. Illegal characters come through as
+/// `TokenKind::Illegal`; lexing stops early if the default `LexerLimits`
+/// are exceeded.
+pub fn lex_with_spans(input: &str) -> Vec<Token> {
+    let mut lexer = Lexer::new(input);
+    let mut tokens = vec![];
+    loop {
+        let tok = lexer.next_token();
+        if tok == TokenKind::EOF {
+            return tokens;
+        }
+        tokens.push(tok);
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::io::BufReader;
+
+    /// A token paired with its span, so that comparing them checks both.
+    fn spanned(tok: Token) -> (Token, Span) {
+        let span = tok.span;
+        (tok, span)
+    }
+
+    fn lex_spanned(input: &str) -> Vec<(Token, Span)> {
+        lex_with_spans(input).into_iter().map(spanned).collect()
+    }
 
     fn check_output_tokens(input: &str, expected_output: Vec<Token>) {
         let mut lexer = Lexer::new(input);
@@ -153,18 +817,547 @@ mod test {
         for (idx, expected_token) in expected_output.into_iter().enumerate() {
             let lexed_token = lexer.next_token();
             assert_eq!(
-                expected_token,
-                lexed_token,
+                expected_token, lexed_token,
                 "[{}] Expected ({:?} {:?}) => Got ({:?} {:?})",
-                idx,
-                expected_token,
-                expected_token.literal(),
-                lexed_token,
-                lexed_token.literal()
+                idx, expected_token, expected_token.literal, lexed_token, lexed_token.literal
             );
         }
     }
 
+    #[test]
+    fn test_lex_with_spans() {
+        assert_eq!(
+            lex_spanned("let x1 = 10;\n  x != y"),
+            vec![
+                (Token::from(TokenKind::Let), Span::new(0, 3)),
+                (Token::make_ident("x1"), Span::new(4, 6)),
+                (Token::from(TokenKind::Assign), Span::new(7, 8)),
+                (Token::make_int("10"), Span::new(9, 11)),
+                (Token::from(TokenKind::Semicolon), Span::new(11, 12)),
+                (Token::make_ident("x"), Span::new(15, 16)),
+                (Token::from(TokenKind::NotEq), Span::new(17, 19)),
+                (Token::make_ident("y"), Span::new(20, 21)),
+            ]
+        );
+        assert_eq!(lex_with_spans("   "), Vec::<Token>::new());
+    }
+
+    #[test]
+    fn test_illegal_characters() {
+        let mut lexer = Lexer::new("a @ “b”");
+        assert_eq!(lexer.next_token(), Token::make_ident("a"));
+        assert_eq!(lexer.take_illegal(), None);
+        assert_eq!(
+            spanned(lexer.next_token()),
+            (Token::new(TokenKind::Illegal, "@"), Span::new(2, 3))
+        );
+        let err = lexer.take_illegal().unwrap();
+        assert_eq!(err, LexerError::IllegalCharacter('@'));
+        assert_eq!(err.to_string(), "Illegal character '@'");
+        assert_eq!(err.suggestion(), None);
+        assert_eq!(lexer.take_illegal(), None);
+        assert_eq!(lexer.next_token(), TokenKind::Illegal);
+        assert_eq!(
+            lexer
+                .take_illegal()
+                .and_then(|err| err.suggestion())
+                .as_deref(),
+            Some("use straight quotes `\"` for strings")
+        );
+        assert_eq!(lexer.next_token(), Token::make_ident("b"));
+        assert_eq!(lexer.next_token(), TokenKind::Illegal);
+        assert_eq!(lexer.next_token(), TokenKind::EOF);
+        assert_eq!(lexer.take_illegal(), None);
+        assert_eq!(lexer.error(), None);
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let source = "/// Doc.\nlet café = \"two\nlines\";\n// done\nf(${x})\n\"${a}\" \u{2}";
+        let mut lexer = Lexer::from_reader(BufReader::with_capacity(1, source.as_bytes()));
+        let mut streamed = vec![];
+        loop {
+            let (tok, span) = spanned(lexer.next_token());
+            if tok == TokenKind::EOF {
+                break;
+            }
+            streamed.push((tok, span, lexer.take_doc()));
+            // Text before the token just lexed has been dropped.
+            assert!(lexer.input.len() < 30, "holding {:?}", lexer.input);
+        }
+        let mut lexer = Lexer::new(source);
+        let mut expected = vec![];
+        loop {
+            let (tok, span) = spanned(lexer.next_token());
+            if tok == TokenKind::EOF {
+                break;
+            }
+            expected.push((tok, span, lexer.take_doc()));
+        }
+        assert_eq!(streamed, expected);
+    }
+
+    #[test]
+    fn test_from_reader_errors() {
+        let limits = LexerLimits {
+            max_input_len: 8,
+            ..LexerLimits::default()
+        };
+        let mut lexer = Lexer::from_reader_with_limits("let x = 1;\nlet y = 2;".as_bytes(), limits);
+        assert_eq!(lexer.next_token(), TokenKind::EOF);
+        assert_eq!(
+            lexer.error(),
+            Some(&LexerError::InputTooLarge { len: 11, max: 8 })
+        );
+
+        let mut lexer = Lexer::from_reader(&b"x\n\"\xff\""[..]);
+        assert_eq!(lexer.next_token(), Token::make_ident("x"));
+        assert_eq!(lexer.next_token(), TokenKind::EOF);
+        assert_eq!(lexer.error().map(LexerError::code), Some("L0006"));
+    }
+
+    #[test]
+    fn test_shebang() {
+        let source = "#!/usr/bin/env monkey\nlet x = 1;";
+        assert_eq!(
+            lex_spanned(source)[..2],
+            [
+                (Token::from(TokenKind::Let), Span::new(22, 25)),
+                (Token::make_ident("x"), Span::new(26, 27)),
+            ]
+        );
+        let mut lexer = Lexer::from_reader(source.as_bytes());
+        assert_eq!(lexer.next_token(), TokenKind::Let);
+        // Only the first line may be a shebang.
+        let mut lexer = Lexer::new(" #!x");
+        assert_eq!(lexer.next_token(), TokenKind::Illegal);
+        assert_eq!(lexer.next_token(), TokenKind::Bang);
+    }
+
+    #[test]
+    fn test_eof_span() {
+        let mut lexer = Lexer::new("ab ");
+        assert_eq!(
+            spanned(lexer.next_token()),
+            (Token::make_ident("ab"), Span::new(0, 2))
+        );
+        assert_eq!(
+            spanned(lexer.next_token()),
+            (Token::from(TokenKind::EOF), Span::new(3, 3))
+        );
+        assert_eq!(
+            spanned(lexer.next_token()),
+            (Token::from(TokenKind::EOF), Span::new(3, 3))
+        );
+    }
+
+    #[test]
+    fn test_multibyte_spans() {
+        assert_eq!(
+            lex_spanned("é + 1 ¬ café"),
+            vec![
+                (Token::make_ident("é"), Span::new(0, 2)),
+                (Token::from(TokenKind::Plus), Span::new(3, 4)),
+                (Token::make_int("1"), Span::new(5, 6)),
+                (Token::new(TokenKind::Illegal, "¬"), Span::new(7, 9)),
+                (Token::make_ident("café"), Span::new(10, 15)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_illegal_characters_advance() {
+        let mut lexer = Lexer::new("@#");
+        assert_eq!(lexer.next_token(), TokenKind::Illegal);
+        assert_eq!(lexer.next_token(), TokenKind::Illegal);
+        assert_eq!(lexer.next_token(), TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_dialect_keywords() {
+        let keywords = Keywords::new()
+            .alias("func", Token::from(TokenKind::Function))
+            .alias("fn", Token::make_ident("fn"))
+            .alias_sequence(
+                "elsif",
+                vec![Token::from(TokenKind::Else), Token::from(TokenKind::If)],
+            );
+        let mut lexer = Lexer::new("func fn elsif x").with_keywords(keywords);
+        let mut tokens = vec![];
+        loop {
+            let tok = spanned(lexer.next_token());
+            tokens.push(tok.clone());
+            if tok.0 == TokenKind::EOF {
+                break;
+            }
+        }
+        assert_eq!(
+            tokens,
+            vec![
+                (Token::from(TokenKind::Function), Span::new(0, 4)),
+                (Token::make_ident("fn"), Span::new(5, 7)),
+                (Token::from(TokenKind::Else), Span::new(8, 13)),
+                (Token::from(TokenKind::If), Span::new(8, 13)),
+                (Token::make_ident("x"), Span::new(14, 15)),
+                (Token::from(TokenKind::EOF), Span::new(15, 15)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_case_insensitive_keywords() {
+        let input = "Let x = TRUE; Fn IFFY";
+        check_output_tokens(
+            input,
+            vec![
+                Token::make_ident("Let"),
+                Token::make_ident("x"),
+                Token::from(TokenKind::Assign),
+                Token::make_ident("TRUE"),
+            ],
+        );
+
+        let features = LanguageFeatures {
+            case_insensitive_keywords: true,
+        };
+        let mut lexer = Lexer::new(input).with_features(features);
+        let expected = vec![
+            Token::from(TokenKind::Let),
+            Token::make_ident("x"),
+            Token::from(TokenKind::Assign),
+            Token::from(TokenKind::True),
+            Token::from(TokenKind::Semicolon),
+            Token::from(TokenKind::Function),
+            Token::make_ident("IFFY"),
+            Token::from(TokenKind::EOF),
+        ];
+        for expected_token in expected {
+            assert_eq!(lexer.next_token(), expected_token);
+        }
+    }
+
+    #[test]
+    fn test_identifier_limit() {
+        let limits = LexerLimits {
+            max_identifier_len: 3,
+            ..LexerLimits::default()
+        };
+        let mut lexer = Lexer::with_limits("abc abcd abc", limits);
+        assert_eq!(lexer.next_token(), Token::make_ident("abc"));
+        assert_eq!(lexer.error(), None);
+        assert_eq!(lexer.next_token(), TokenKind::EOF);
+        assert_eq!(
+            lexer.error(),
+            Some(&LexerError::IdentifierTooLong { max: 3 })
+        );
+        assert_eq!(lexer.next_token(), TokenKind::EOF);
+    }
+
+    #[test]
+    fn test_number_limit() {
+        let limits = LexerLimits {
+            max_number_len: 2,
+            ..LexerLimits::default()
+        };
+        let mut lexer = Lexer::with_limits("10 + 100", limits);
+        assert_eq!(lexer.next_token(), Token::make_int("10"));
+        assert_eq!(lexer.next_token(), TokenKind::Plus);
+        assert_eq!(lexer.next_token(), TokenKind::EOF);
+        assert_eq!(lexer.error(), Some(&LexerError::NumberTooLong { max: 2 }));
+    }
+
+    #[test]
+    fn test_string_limit() {
+        let limits = LexerLimits {
+            max_string_len: 3,
+            ..LexerLimits::default()
+        };
+        let mut lexer = Lexer::with_limits(r#""abc" r"abc" "abc${x}abc" "abcd""#, limits);
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::String, "abc".to_owned())
+        );
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::String, "abc".to_owned())
+        );
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::InterpolationStart, "abc".to_owned())
+        );
+        assert_eq!(lexer.next_token(), Token::make_ident("x"));
+        assert_eq!(
+            lexer.next_token(),
+            Token::new(TokenKind::InterpolationEnd, "abc".to_owned())
+        );
+        assert_eq!(lexer.error(), None);
+        assert_eq!(lexer.next_token(), TokenKind::EOF);
+        assert_eq!(lexer.error(), Some(&LexerError::StringTooLong { max: 3 }));
+
+        for source in [r#"r"abcd""#, r#""${x}abcd""#] {
+            let mut lexer = Lexer::with_limits(source, limits);
+            while lexer.next_token() != TokenKind::EOF {}
+            assert_eq!(lexer.error().map(LexerError::code), Some("L0007"));
+        }
+    }
+
+    #[test]
+    fn test_input_limit() {
+        let limits = LexerLimits {
+            max_input_len: 4,
+            ..LexerLimits::default()
+        };
+        let mut lexer = Lexer::with_limits("let x = 5;", limits);
+        assert_eq!(lexer.next_token(), TokenKind::EOF);
+        assert_eq!(
+            lexer.error(),
+            Some(&LexerError::InputTooLarge { len: 10, max: 4 })
+        );
+
+        let mut lexer = Lexer::with_limits("let", limits);
+        assert_eq!(lexer.next_token(), TokenKind::Let);
+        assert_eq!(lexer.error(), None);
+    }
+
+    #[test]
+    fn test_try_catch_tokens() {
+        let input = "try { x } catch (e) { e }";
+        let expected_output = vec![
+            Token::from(TokenKind::Try),
+            Token::from(TokenKind::LBrace),
+            Token::make_ident("x"),
+            Token::from(TokenKind::RBrace),
+            Token::from(TokenKind::Catch),
+            Token::from(TokenKind::LParen),
+            Token::make_ident("e"),
+            Token::from(TokenKind::RParen),
+            Token::from(TokenKind::LBrace),
+            Token::make_ident("e"),
+            Token::from(TokenKind::RBrace),
+            Token::from(TokenKind::EOF),
+        ];
+        check_output_tokens(input, expected_output);
+    }
+
+    #[test]
+    fn test_strings_and_brackets() {
+        check_output_tokens(
+            r#""hello"[1:3] "" "a b""#,
+            vec![
+                Token::new(TokenKind::String, "hello".to_owned()),
+                Token::from(TokenKind::LBracket),
+                Token::make_int("1"),
+                Token::from(TokenKind::Colon),
+                Token::make_int("3"),
+                Token::from(TokenKind::RBracket),
+                Token::new(TokenKind::String, "".to_owned()),
+                Token::new(TokenKind::String, "a b".to_owned()),
+                Token::from(TokenKind::EOF),
+            ],
+        );
+        check_output_tokens(
+            "a |> f(1) | b",
+            vec![
+                Token::make_ident("a"),
+                Token::from(TokenKind::Pipe),
+                Token::make_ident("f"),
+                Token::from(TokenKind::LParen),
+                Token::make_int("1"),
+                Token::from(TokenKind::RParen),
+                Token::from(TokenKind::Bar),
+                Token::make_ident("b"),
+                Token::from(TokenKind::EOF),
+            ],
+        );
+        check_output_tokens(
+            "a & b ^ ~c << 1 >> 2 < 3 > 4",
+            vec![
+                Token::make_ident("a"),
+                Token::from(TokenKind::Ampersand),
+                Token::make_ident("b"),
+                Token::from(TokenKind::Caret),
+                Token::from(TokenKind::Tilde),
+                Token::make_ident("c"),
+                Token::from(TokenKind::ShiftLeft),
+                Token::make_int("1"),
+                Token::from(TokenKind::ShiftRight),
+                Token::make_int("2"),
+                Token::from(TokenKind::Lt),
+                Token::make_int("3"),
+                Token::from(TokenKind::Gt),
+                Token::make_int("4"),
+                Token::from(TokenKind::EOF),
+            ],
+        );
+        check_output_tokens(
+            "a ** b * c",
+            vec![
+                Token::make_ident("a"),
+                Token::from(TokenKind::Power),
+                Token::make_ident("b"),
+                Token::from(TokenKind::Asterisk),
+                Token::make_ident("c"),
+                Token::from(TokenKind::EOF),
+            ],
+        );
+        check_output_tokens(
+            "a.b 1..n 1..=n",
+            vec![
+                Token::make_ident("a"),
+                Token::from(TokenKind::Dot),
+                Token::make_ident("b"),
+                Token::make_int("1"),
+                Token::from(TokenKind::DotDot),
+                Token::make_ident("n"),
+                Token::make_int("1"),
+                Token::from(TokenKind::DotDotEq),
+                Token::make_ident("n"),
+                Token::from(TokenKind::EOF),
+            ],
+        );
+        assert_eq!(
+            lex_spanned(r#"x "héllo""#),
+            vec![
+                (Token::make_ident("x"), Span::new(0, 1)),
+                (
+                    Token::new(TokenKind::String, "héllo".to_owned()),
+                    Span::new(2, 10)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        check_output_tokens(
+            "let café = 変数1 + _x + x_2;",
+            vec![
+                Token::from(TokenKind::Let),
+                Token::make_ident("café"),
+                Token::from(TokenKind::Assign),
+                Token::make_ident("変数1"),
+                Token::from(TokenKind::Plus),
+                Token::make_ident("_x"),
+                Token::from(TokenKind::Plus),
+                Token::make_ident("x_2"),
+                Token::from(TokenKind::Semicolon),
+                Token::from(TokenKind::EOF),
+            ],
+        );
+        // Emoji are not identifier characters, and combining marks may only
+        // continue one.
+        check_output_tokens(
+            "a😀b e\u{301}",
+            vec![
+                Token::make_ident("a"),
+                Token::new(TokenKind::Illegal, "😀"),
+                Token::make_ident("b"),
+                Token::make_ident("e\u{301}"),
+                Token::from(TokenKind::EOF),
+            ],
+        );
+        let source = "let 名前 = 😀;\nx";
+        let spans: Vec<Span> = lex_with_spans(source)
+            .into_iter()
+            .map(|tok| tok.span)
+            .collect();
+        assert_eq!(spans[1], Span::new(4, 10));
+        assert_eq!(spans[1].location(source), (1, 5));
+        assert_eq!(spans[3].location(source), (1, 10));
+        assert_eq!(spans[4].location(source), (1, 11));
+        assert_eq!(spans[5].location(source), (2, 1));
+    }
+
+    #[test]
+    fn test_interpolated_strings() {
+        check_output_tokens(
+            r#""a ${x + 1} b ${f({})}" "${"${y}"}$ {}""#,
+            vec![
+                Token::new(TokenKind::InterpolationStart, "a ".to_owned()),
+                Token::make_ident("x"),
+                Token::from(TokenKind::Plus),
+                Token::make_int("1"),
+                Token::new(TokenKind::InterpolationMid, " b ".to_owned()),
+                Token::make_ident("f"),
+                Token::from(TokenKind::LParen),
+                Token::from(TokenKind::LBrace),
+                Token::from(TokenKind::RBrace),
+                Token::from(TokenKind::RParen),
+                Token::new(TokenKind::InterpolationEnd, "".to_owned()),
+                Token::new(TokenKind::InterpolationStart, "".to_owned()),
+                Token::new(TokenKind::InterpolationStart, "".to_owned()),
+                Token::make_ident("y"),
+                Token::new(TokenKind::InterpolationEnd, "".to_owned()),
+                Token::new(TokenKind::InterpolationEnd, "$ {}".to_owned()),
+                Token::from(TokenKind::EOF),
+            ],
+        );
+        assert_eq!(
+            lex_spanned(r#""a${x}b""#),
+            vec![
+                (
+                    Token::new(TokenKind::InterpolationStart, "a".to_owned()),
+                    Span::new(0, 4)
+                ),
+                (Token::make_ident("x"), Span::new(4, 5)),
+                (
+                    Token::new(TokenKind::InterpolationEnd, "b".to_owned()),
+                    Span::new(5, 8)
+                ),
+            ]
+        );
+
+        let mut lexer = Lexer::new(r#""a ${x"#);
+        while lexer.next_token() != TokenKind::EOF {}
+        assert_eq!(lexer.error(), Some(&LexerError::UnterminatedString));
+    }
+
+    #[test]
+    fn test_raw_strings() {
+        check_output_tokens(
+            r#"r"C:\path\n" r"${x}" r "#,
+            vec![
+                Token::new(TokenKind::String, r"C:\path\n".to_owned()),
+                Token::new(TokenKind::String, "${x}".to_owned()),
+                Token::make_ident("r"),
+                Token::from(TokenKind::EOF),
+            ],
+        );
+        let mut lexer = Lexer::new(r#"r"open"#);
+        assert_eq!(lexer.next_token(), TokenKind::EOF);
+        assert_eq!(lexer.error(), Some(&LexerError::UnterminatedString));
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let mut lexer = Lexer::new(r#"let s = "abc"#);
+        while lexer.next_token() != TokenKind::EOF {}
+        assert_eq!(lexer.error(), Some(&LexerError::UnterminatedString));
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "a // one\n/ b //\n// two";
+        let expected_output = vec![
+            Token::make_ident("a"),
+            Token::from(TokenKind::Slash),
+            Token::make_ident("b"),
+            Token::from(TokenKind::EOF),
+        ];
+        check_output_tokens(input, expected_output);
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let input = "/// Adds one.\n///\n///   Indented\r\nlet // plain\n//// not doc\nx";
+        let mut lexer = Lexer::new(input);
+        assert_eq!(lexer.next_token(), TokenKind::Let);
+        assert_eq!(lexer.take_doc().as_deref(), Some("Adds one.\n\n  Indented"));
+        assert_eq!(lexer.take_doc(), None);
+        assert_eq!(lexer.next_token(), Token::make_ident("x"));
+        assert_eq!(lexer.take_doc(), None);
+    }
+
     #[test]
     fn test_eq_op_tokens() {
         let input = r#"
@@ -174,14 +1367,14 @@ mod test {
         "#;
         let expected_output = vec![
             Token::make_int("10"),
-            Token::Eq,
+            Token::from(TokenKind::Eq),
             Token::make_int("10"),
-            Token::Semicolon,
+            Token::from(TokenKind::Semicolon),
             Token::make_int("10"),
-            Token::NotEq,
+            Token::from(TokenKind::NotEq),
             Token::make_int("9"),
-            Token::Semicolon,
-            Token::EOF,
+            Token::from(TokenKind::Semicolon),
+            Token::from(TokenKind::EOF),
         ];
         check_output_tokens(input, expected_output);
     }
@@ -198,43 +1391,43 @@ mod test {
         let result = add(five, ten);
         "#;
         let expected_output = vec![
-            Token::Let,
+            Token::from(TokenKind::Let),
             Token::make_ident("five"),
-            Token::Assign,
+            Token::from(TokenKind::Assign),
             Token::make_int("5"),
-            Token::Semicolon,
-            Token::Let,
+            Token::from(TokenKind::Semicolon),
+            Token::from(TokenKind::Let),
             Token::make_ident("ten"),
-            Token::Assign,
+            Token::from(TokenKind::Assign),
             Token::make_int("10"),
-            Token::Semicolon,
-            Token::Let,
+            Token::from(TokenKind::Semicolon),
+            Token::from(TokenKind::Let),
             Token::make_ident("add"),
-            Token::Assign,
-            Token::Function,
-            Token::LParen,
+            Token::from(TokenKind::Assign),
+            Token::from(TokenKind::Function),
+            Token::from(TokenKind::LParen),
             Token::make_ident("x"),
-            Token::Comma,
+            Token::from(TokenKind::Comma),
             Token::make_ident("y"),
-            Token::RParen,
-            Token::LBrace,
+            Token::from(TokenKind::RParen),
+            Token::from(TokenKind::LBrace),
             Token::make_ident("x"),
-            Token::Plus,
+            Token::from(TokenKind::Plus),
             Token::make_ident("y"),
-            Token::Semicolon,
-            Token::RBrace,
-            Token::Semicolon,
-            Token::Let,
+            Token::from(TokenKind::Semicolon),
+            Token::from(TokenKind::RBrace),
+            Token::from(TokenKind::Semicolon),
+            Token::from(TokenKind::Let),
             Token::make_ident("result"),
-            Token::Assign,
+            Token::from(TokenKind::Assign),
             Token::make_ident("add"),
-            Token::LParen,
+            Token::from(TokenKind::LParen),
             Token::make_ident("five"),
-            Token::Comma,
+            Token::from(TokenKind::Comma),
             Token::make_ident("ten"),
-            Token::RParen,
-            Token::Semicolon,
-            Token::EOF,
+            Token::from(TokenKind::RParen),
+            Token::from(TokenKind::Semicolon),
+            Token::from(TokenKind::EOF),
         ];
         check_output_tokens(input, expected_output);
     }
@@ -253,36 +1446,36 @@ mod test {
         }
         "#;
         let expected_output = vec![
-            Token::Bang,
-            Token::Minus,
-            Token::Slash,
-            Token::Asterisk,
+            Token::from(TokenKind::Bang),
+            Token::from(TokenKind::Minus),
+            Token::from(TokenKind::Slash),
+            Token::from(TokenKind::Asterisk),
             Token::make_int("5"),
-            Token::Semicolon,
+            Token::from(TokenKind::Semicolon),
             Token::make_int("5"),
-            Token::Lt,
+            Token::from(TokenKind::Lt),
             Token::make_int("10"),
-            Token::Gt,
+            Token::from(TokenKind::Gt),
             Token::make_int("5"),
-            Token::Semicolon,
-            Token::If,
-            Token::LParen,
+            Token::from(TokenKind::Semicolon),
+            Token::from(TokenKind::If),
+            Token::from(TokenKind::LParen),
             Token::make_int("5"),
-            Token::Lt,
+            Token::from(TokenKind::Lt),
             Token::make_int("10"),
-            Token::RParen,
-            Token::LBrace,
-            Token::Return,
-            Token::True,
-            Token::Semicolon,
-            Token::RBrace,
-            Token::Else,
-            Token::LBrace,
-            Token::Return,
-            Token::False,
-            Token::Semicolon,
-            Token::RBrace,
-            Token::EOF,
+            Token::from(TokenKind::RParen),
+            Token::from(TokenKind::LBrace),
+            Token::from(TokenKind::Return),
+            Token::from(TokenKind::True),
+            Token::from(TokenKind::Semicolon),
+            Token::from(TokenKind::RBrace),
+            Token::from(TokenKind::Else),
+            Token::from(TokenKind::LBrace),
+            Token::from(TokenKind::Return),
+            Token::from(TokenKind::False),
+            Token::from(TokenKind::Semicolon),
+            Token::from(TokenKind::RBrace),
+            Token::from(TokenKind::EOF),
         ];
         check_output_tokens(input, expected_output);
     }
@@ -1,8 +1,24 @@
+#[cfg(test)]
+mod arbitrary;
 pub mod ast;
+pub mod check;
+pub mod diagnostic;
+pub mod doc;
+pub mod explain;
+pub mod features;
+pub mod incremental;
+pub mod json;
 pub mod lexer;
+pub mod lint;
 pub mod parser;
+pub mod printer;
 pub mod repl;
+pub mod span;
+pub mod symbol_table;
 pub mod token;
+pub mod version;
+pub mod visit;
 
-pub use lexer::Lexer;
-pub use token::Token;
+pub use lexer::{lex_with_spans, Lexer};
+pub use span::Span;
+pub use token::{Token, TokenKind};
@@ -0,0 +1,584 @@
+//! Static checks over a parsed program, as run by `monkey lint`.
+
+use crate::ast::{BlockStatement, Expression, Identifier, Program, Statement};
+use crate::span::Span;
+use crate::symbol_table::SymbolTable;
+use crate::token::{Token, TokenKind};
+use crate::visit::{self, Visitor};
+use std::fmt::{self, Display};
+
+/// The builtin functions every program can call without binding them.
+pub const BUILTINS: &[&str] = &["first", "last", "len", "push", "puts", "rest"];
+
+/// The kinds of problem the linter reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    /// A `let` binding which is never read.
+    UnusedBinding,
+    /// A statement following a `return` in the same block.
+    UnreachableCode,
+    /// An `if` condition which is a literal, so always takes the same branch.
+    ConstantCondition,
+    /// A `let` or `catch` binding reusing a name which is already bound.
+    ShadowedBinding,
+    /// A binding reusing the name of a `const`.
+    ShadowedConstant,
+    /// A name which is neither a builtin nor bound where it is used. A
+    /// function may use a top-level binding from later in the program.
+    UnknownIdentifier,
+}
+
+impl LintKind {
+    /// The stable, machine-readable name of the lint.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintKind::UnusedBinding => "unused-binding",
+            LintKind::UnreachableCode => "unreachable-code",
+            LintKind::ConstantCondition => "constant-condition",
+            LintKind::ShadowedBinding => "shadowed-binding",
+            LintKind::ShadowedConstant => "shadowed-constant",
+            LintKind::UnknownIdentifier => "unknown-identifier",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub message: String,
+    /// The name or statement the lint is about.
+    pub span: Span,
+    /// A fix specific to this lint, rather than to its kind.
+    pub suggestion: Option<String>,
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "warning[{}]: {}", self.kind.name(), self.message)
+    }
+}
+
+/// Run every lint over `program`.
+pub fn lint(program: &Program) -> Vec<Lint> {
+    let mut symbols = SymbolTable::new();
+    for (index, name) in BUILTINS.iter().enumerate() {
+        symbols.define_builtin(index, name);
+    }
+    let mut linter = Linter {
+        symbols,
+        bindings: vec![vec![]],
+        functions: 0,
+        forward: vec![],
+        statement: Span::default(),
+        lints: vec![],
+    };
+    linter.visit_program(program);
+    // Whatever wasn't bound by the end of the program never will be.
+    for ident in std::mem::take(&mut linter.forward) {
+        linter.report_unknown(&ident);
+    }
+    linter.close_scope();
+    linter.lints
+}
+
+struct Binding {
+    name: String,
+    span: Span,
+    used: bool,
+    constant: bool,
+}
+
+struct Linter {
+    /// The names visible at the current point.
+    symbols: SymbolTable,
+    /// What is known about each binding in `symbols`, by the depth of its
+    /// scope and then its index.
+    bindings: Vec<Vec<Binding>>,
+    /// How many function bodies enclose the current point.
+    functions: usize,
+    /// Unknown names used in function bodies, which a top-level binding
+    /// later on may yet define before the function is called.
+    forward: Vec<Identifier>,
+    /// The first token of the statement being linted.
+    statement: Span,
+    lints: Vec<Lint>,
+}
+
+impl Linter {
+    fn report(&mut self, kind: LintKind, message: String, span: Span) {
+        self.lints.push(Lint {
+            kind,
+            message,
+            span,
+            suggestion: None,
+        });
+    }
+
+    /// The binding `name` refers to at the current point, by the depth of
+    /// its scope and its index there. `None` for builtins and unknown names.
+    fn resolve(&mut self, name: &Identifier) -> Option<(usize, usize)> {
+        let symbol = self.symbols.resolve(&name.0)?;
+        let (depth, symbol) = self.symbols.definition(&symbol)?;
+        Some((depth, symbol.index))
+    }
+
+    fn bind(&mut self, name: &Identifier, used: bool, constant: bool) {
+        if let Some((depth, index)) = self.resolve(name) {
+            if self.bindings[depth][index].constant {
+                self.report(
+                    LintKind::ShadowedConstant,
+                    format!("`{}` shadows a constant", name),
+                    name.1,
+                );
+            } else {
+                self.report(
+                    LintKind::ShadowedBinding,
+                    format!("`{}` shadows an earlier binding", name),
+                    name.1,
+                );
+            }
+            // Nothing can read the earlier binding once it is replaced in
+            // its own scope.
+            if depth == self.symbols.depth() {
+                self.report_unused(depth, index);
+            }
+        }
+        // A function body before it may have read the binding already.
+        let mut used = used;
+        if self.symbols.depth() == 0 {
+            let before = self.forward.len();
+            self.forward.retain(|ident| *ident != *name);
+            used |= self.forward.len() < before;
+        }
+        self.symbols.define(&name.0);
+        self.bindings
+            .last_mut()
+            .expect("no open scope")
+            .push(Binding {
+                name: name.0.clone(),
+                span: name.1,
+                used,
+                constant,
+            });
+    }
+
+    fn report_unknown(&mut self, ident: &Identifier) {
+        let names = self.bindings.iter().flatten().map(|b| b.name.as_str());
+        let suggestion = closest(&ident.0, names.chain(BUILTINS.iter().copied()));
+        self.lints.push(Lint {
+            kind: LintKind::UnknownIdentifier,
+            message: format!("`{}` is not defined", ident),
+            span: ident.1,
+            suggestion: suggestion.map(|name| format!("did you mean `{}`?", name)),
+        });
+    }
+
+    fn open_scope(&mut self) {
+        self.symbols.push_scope();
+        self.bindings.push(vec![]);
+    }
+
+    fn close_scope(&mut self) {
+        let depth = self.bindings.len() - 1;
+        for index in 0..self.bindings[depth].len() {
+            self.report_unused(depth, index);
+        }
+        self.bindings.pop();
+        if depth > 0 {
+            self.symbols.pop_scope();
+        }
+    }
+
+    /// Report the binding if it was never used, at most once.
+    fn report_unused(&mut self, depth: usize, index: usize) {
+        let binding = &mut self.bindings[depth][index];
+        if !binding.used {
+            binding.used = true;
+            let message = format!("`{}` is never used", binding.name);
+            let span = binding.span;
+            self.report(LintKind::UnusedBinding, message, span);
+        }
+    }
+
+    fn check_reachable(&mut self, statements: &[Statement]) {
+        let returns = statements
+            .iter()
+            .position(|stmt| matches!(stmt, Statement::Return { .. }));
+        if let Some(idx) = returns {
+            let unreachable = statements.len() - idx - 1;
+            if unreachable > 0 {
+                self.report(
+                    LintKind::UnreachableCode,
+                    format!(
+                        "{} statement{} after `return` will never run",
+                        unreachable,
+                        if unreachable == 1 { "" } else { "s" }
+                    ),
+                    statement_token(&statements[idx + 1]).span,
+                );
+            }
+        }
+    }
+}
+
+impl Visitor for Linter {
+    fn visit_program(&mut self, program: &Program) {
+        self.check_reachable(&program.statements);
+        visit::walk_program(self, program);
+    }
+
+    fn visit_block_statement(&mut self, block: &BlockStatement) {
+        self.check_reachable(&block.statements);
+        visit::walk_block_statement(self, block);
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement) {
+        let outer = std::mem::replace(&mut self.statement, statement_token(stmt).span);
+        match stmt {
+            // The value is visited first, so `let x = x + 1` reads the
+            // earlier `x`.
+            Statement::Let {
+                token, name, value, ..
+            } => {
+                self.visit_expression(value);
+                self.bind(name, false, *token == TokenKind::Const);
+            }
+            Statement::Destructure {
+                token,
+                pattern,
+                value,
+            } => {
+                self.visit_expression(value);
+                for name in pattern.bindings() {
+                    self.bind(name, false, *token == TokenKind::Const);
+                }
+            }
+            _ => self.visit_other_statement(stmt),
+        }
+        self.statement = outer;
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::If { condition, .. } => {
+                let value = match **condition {
+                    Expression::Boolean(value) => Some(value.to_string()),
+                    Expression::IntegerLiteral(_) => Some("true".to_string()),
+                    _ => None,
+                };
+                if let Some(value) = value {
+                    self.report(
+                        LintKind::ConstantCondition,
+                        format!("`if ({})` is always {}", condition, value),
+                        self.statement,
+                    );
+                }
+                visit::walk_expression(self, expr);
+            }
+            // The catch binding is only visible inside its handler.
+            Expression::Try {
+                body,
+                binding,
+                handler,
+            } => {
+                self.visit_block_statement(body);
+                self.open_scope();
+                // Ignoring the error is deliberate often enough that an
+                // unused catch binding isn't worth a warning.
+                self.bind(binding, true, false);
+                self.visit_block_statement(handler);
+                self.close_scope();
+            }
+            // Parameters are part of a function's signature, so go unused
+            // often enough that they aren't warned about either.
+            Expression::Function {
+                parameters, body, ..
+            } => {
+                self.open_scope();
+                self.functions += 1;
+                for parameter in parameters {
+                    self.bind(parameter, true, false);
+                }
+                self.visit_block_statement(body);
+                self.functions -= 1;
+                self.close_scope();
+            }
+            _ => self.visit_other_expression(expr),
+        }
+    }
+
+    fn visit_identifier(&mut self, ident: &Identifier) {
+        if let Some((depth, index)) = self.resolve(ident) {
+            self.bindings[depth][index].used = true;
+            return;
+        }
+        if BUILTINS.contains(&ident.0.as_str()) {
+            return;
+        }
+        if self.functions > 0 {
+            self.forward.push(ident.clone());
+        } else {
+            self.report_unknown(ident);
+        }
+    }
+}
+
+fn statement_token(stmt: &Statement) -> &Token {
+    match stmt {
+        Statement::Let { token, .. }
+        | Statement::Return { token, .. }
+        | Statement::Expression { token, .. }
+        | Statement::Destructure { token, .. } => token,
+    }
+}
+
+/// The candidate closest to `name` by edit distance, if any is close enough
+/// to be a likely typo. Ties go to the first candidate.
+fn closest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max = (name.chars().count() / 3).max(1);
+    let mut best: Option<(usize, &str)> = None;
+    for candidate in candidates {
+        let distance = edit_distance(name, candidate);
+        if distance <= max && best.map_or(true, |(best, _)| distance < best) {
+            best = Some((distance, candidate));
+        }
+    }
+    best.map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between `a` and `b`, in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != *b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diagnostic::Diagnostic;
+    use crate::parser::Parser;
+
+    fn lints_for(input: &str) -> Vec<(LintKind, String)> {
+        let mut parser = Parser::from_input(input);
+        let program = parser.parse_program().unwrap();
+        assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+        lint(&program)
+            .into_iter()
+            .map(|lint| (lint.kind, lint.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("lenth", "length"), 1);
+        assert_eq!(edit_distance("length", "len"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("été", "ete"), 2);
+    }
+
+    #[test]
+    fn test_unknown_identifier() {
+        let lints = lint(
+            &Parser::from_input("let length = 5; lenth; put(length)")
+                .parse_program()
+                .unwrap(),
+        );
+        let unknown: Vec<(&str, Option<&str>)> = lints
+            .iter()
+            .map(|lint| (lint.message.as_str(), lint.suggestion.as_deref()))
+            .collect();
+        assert_eq!(
+            unknown,
+            vec![
+                ("`lenth` is not defined", Some("did you mean `length`?")),
+                ("`put` is not defined", Some("did you mean `puts`?")),
+            ]
+        );
+        assert_eq!(
+            lints_for("frobnicate(1)"),
+            vec![(
+                LintKind::UnknownIdentifier,
+                "`frobnicate` is not defined".to_owned()
+            )]
+        );
+        // Names bound later, or by recursion, are known by the time a
+        // function runs.
+        assert!(
+            lints_for("let f = fn(n) { f(n) + g(n) }; let g = fn(n) { len(n) }; f(1)").is_empty()
+        );
+        assert!(lints_for("let f = fn() { g() }; let g = fn() { 1 }; f()").is_empty());
+        // But only if the binding is at the top level.
+        assert_eq!(
+            lints_for("let f = fn() { let h = fn() { g }; let g = 1; h() }; f()"),
+            vec![
+                (LintKind::UnusedBinding, "`g` is never used".to_owned()),
+                (LintKind::UnknownIdentifier, "`g` is not defined".to_owned()),
+            ]
+        );
+        // A parameter is only visible inside its function.
+        assert_eq!(
+            lints_for("let h = fn(a) { a }; h(a)"),
+            vec![(LintKind::UnknownIdentifier, "`a` is not defined".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_clean_program() {
+        assert!(lints_for("let x = 1; let y = x + 1; y").is_empty());
+        assert!(lints_for("let x = 1; try { x } catch (e) { 0 }").is_empty());
+    }
+
+    #[test]
+    fn test_unused_binding() {
+        assert_eq!(
+            lints_for("let x = 1; let y = 2; y"),
+            vec![(LintKind::UnusedBinding, "`x` is never used".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_shadowed_binding() {
+        assert_eq!(
+            lints_for("let x = 1; let x = x + 1; x"),
+            vec![(
+                LintKind::ShadowedBinding,
+                "`x` shadows an earlier binding".to_owned()
+            )]
+        );
+        assert_eq!(
+            lints_for("let x = 1; let x = 2; x"),
+            vec![
+                (
+                    LintKind::ShadowedBinding,
+                    "`x` shadows an earlier binding".to_owned()
+                ),
+                (LintKind::UnusedBinding, "`x` is never used".to_owned()),
+            ]
+        );
+        assert_eq!(
+            lints_for("let e = 1; try { e } catch (e) { e }"),
+            vec![(
+                LintKind::ShadowedBinding,
+                "`e` shadows an earlier binding".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_destructured_bindings() {
+        assert_eq!(
+            lints_for("let arr = [1, [2, 3]]; let p = arr; let [a, [b, c]] = arr; let {x, y} = p; a + c + y"),
+            vec![
+                (LintKind::UnusedBinding, "`b` is never used".to_owned()),
+                (LintKind::UnusedBinding, "`x` is never used".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shadowed_constant() {
+        assert_eq!(
+            lints_for("const x = 1; let f = fn(x) { x }; f(x)"),
+            vec![(
+                LintKind::ShadowedConstant,
+                "`x` shadows a constant".to_owned()
+            )]
+        );
+        assert_eq!(
+            lints_for("let x = 1; const x = x; x"),
+            vec![(
+                LintKind::ShadowedBinding,
+                "`x` shadows an earlier binding".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_function_scope() {
+        assert_eq!(
+            lints_for("let x = 1; let f = fn(x, y) { let z = 2; x }; f"),
+            vec![
+                (
+                    LintKind::ShadowedBinding,
+                    "`x` shadows an earlier binding".to_owned()
+                ),
+                (LintKind::UnusedBinding, "`z` is never used".to_owned()),
+                (LintKind::UnusedBinding, "`x` is never used".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unreachable_code() {
+        assert_eq!(
+            lints_for("let x = 1; if (x) { return 1; 2; 3 }"),
+            vec![(
+                LintKind::UnreachableCode,
+                "2 statements after `return` will never run".to_owned()
+            )]
+        );
+        assert_eq!(
+            lints_for("let x = 1; return; x"),
+            vec![(
+                LintKind::UnreachableCode,
+                "1 statement after `return` will never run".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_constant_condition() {
+        assert_eq!(
+            lints_for("let x = 1; if (false) { 1 }; if (1) { 2 }; if (x) { 3 }"),
+            vec![
+                (
+                    LintKind::ConstantCondition,
+                    "`if (false)` is always false".to_owned()
+                ),
+                (
+                    LintKind::ConstantCondition,
+                    "`if (1)` is always true".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        let source = "let x = 1;\nlet f = fn() { return 1; y };\nlet f = lenn;\nif (true) { f }";
+        let lints = lint(&Parser::from_input(source).parse_program().unwrap());
+        let spans: Vec<(LintKind, (usize, usize))> = lints
+            .iter()
+            .map(|lint| (lint.kind, lint.span.location(source)))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (LintKind::UnreachableCode, (2, 26)),
+                (LintKind::UnknownIdentifier, (3, 9)),
+                (LintKind::ShadowedBinding, (3, 5)),
+                (LintKind::UnusedBinding, (2, 5)),
+                (LintKind::ConstantCondition, (4, 1)),
+                (LintKind::UnknownIdentifier, (2, 26)),
+                (LintKind::UnusedBinding, (1, 5)),
+            ]
+        );
+
+        let json = Diagnostic::from(&lints[2]).to_json("a.monkey", source);
+        assert!(
+            json.contains(r#""span":{"start":45,"end":46,"line":3,"column":5}"#),
+            "{}",
+            json
+        );
+    }
+}
@@ -1,43 +1,137 @@
-use crate::ast::{BlockStatement, Expression, Identifier, Program, Statement};
-use crate::lexer::Lexer;
+use crate::ast::{BlockStatement, Expression, Identifier, Pattern, Program, Statement, StringPart};
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{Lexer, LexerError, TokenStream};
+use crate::span::Span;
 use std::fmt::{self, Display};
+use std::io::BufRead;
+use std::mem;
 
-use crate::token::Token;
+use crate::token::{Token, TokenKind};
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub enum Precedence {
     Lowest,
+    Pipe,
+    Range,
     Equals,
     LessGreater,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
     Sum,
     Product,
+    Power,
     Prefix,
     Call,
+    Index,
 }
 
 impl Precedence {
-    pub fn for_token(token: &Token) -> Self {
-        match token {
-            Token::Eq | Token::NotEq => Precedence::Equals,
-            Token::Gt | Token::Lt => Precedence::LessGreater,
-            Token::Plus | Token::Minus => Precedence::Sum,
-            Token::Slash | Token::Asterisk => Precedence::Product,
+    pub fn for_kind(kind: TokenKind) -> Self {
+        match kind {
+            TokenKind::Eq | TokenKind::NotEq => Precedence::Equals,
+            TokenKind::Gt | TokenKind::Lt => Precedence::LessGreater,
+            TokenKind::Bar => Precedence::BitOr,
+            TokenKind::Caret => Precedence::BitXor,
+            TokenKind::Ampersand => Precedence::BitAnd,
+            TokenKind::ShiftLeft | TokenKind::ShiftRight => Precedence::Shift,
+            TokenKind::Plus | TokenKind::Minus => Precedence::Sum,
+            TokenKind::Slash | TokenKind::Asterisk => Precedence::Product,
+            TokenKind::Power => Precedence::Power,
+            TokenKind::Pipe => Precedence::Pipe,
+            TokenKind::DotDot | TokenKind::DotDotEq => Precedence::Range,
+            TokenKind::LParen | TokenKind::Dot => Precedence::Call,
+            TokenKind::LBracket => Precedence::Index,
             _ => Precedence::Lowest,
         }
     }
+
+    /// The tier just below this one, or `Lowest` for `Lowest` itself.
+    pub fn lower(self) -> Self {
+        match self {
+            Precedence::Lowest | Precedence::Pipe => Precedence::Lowest,
+            Precedence::Range => Precedence::Pipe,
+            Precedence::Equals => Precedence::Range,
+            Precedence::LessGreater => Precedence::Equals,
+            Precedence::BitOr => Precedence::LessGreater,
+            Precedence::BitXor => Precedence::BitOr,
+            Precedence::BitAnd => Precedence::BitXor,
+            Precedence::Shift => Precedence::BitAnd,
+            Precedence::Sum => Precedence::Shift,
+            Precedence::Product => Precedence::Sum,
+            Precedence::Power => Precedence::Product,
+            Precedence::Prefix => Precedence::Power,
+            Precedence::Call => Precedence::Prefix,
+            Precedence::Index => Precedence::Call,
+        }
+    }
+
+    /// Whether a chain of `kind` groups from the right, as `a ** b ** c`
+    /// does. Every other infix operator groups from the left.
+    pub fn is_right_associative(kind: TokenKind) -> bool {
+        matches!(kind, TokenKind::Power)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum ParserError {
-    ExpectedToken { expected: Token, saw: Token },
+    ExpectedToken {
+        expected: TokenKind,
+        saw: Token,
+    },
     ExpectedIdent(Token),
     IntegerParseFailure(String),
     UnhandledPrefix(Token),
     UnhandledExpression(Token),
+    Lexer(LexerError),
+    NestingTooDeep(usize),
+    /// Syntax which `ParserOptions` turned off.
+    Disabled(Extension),
 }
 
 type ParserResult<T> = Result<T, ParserError>;
 
+impl ParserError {
+    /// The stable code identifying this kind of error, for `monkey explain`.
+    /// Codes are never reused once assigned.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParserError::ExpectedToken { .. } => "P0001",
+            ParserError::ExpectedIdent(_) => "P0002",
+            ParserError::UnhandledPrefix(_) => "P0003",
+            ParserError::IntegerParseFailure(_) => "P0004",
+            ParserError::UnhandledExpression(_) => "P0005",
+            ParserError::NestingTooDeep(_) => "P0006",
+            ParserError::Disabled(_) => "P0007",
+            ParserError::Lexer(err) => err.code(),
+        }
+    }
+
+    /// A hint at how to fix the error, where there is an obvious one.
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            ParserError::ExpectedToken { saw, .. } if *saw == TokenKind::Assign => {
+                Some("did you mean `==`?".to_owned())
+            }
+            ParserError::ExpectedToken { expected, .. } => match expected {
+                TokenKind::InterpolationMid | TokenKind::InterpolationEnd => {
+                    Some("close the interpolation with `}`".to_owned())
+                }
+                _ => expected
+                    .literal()
+                    .map(|literal| format!("insert `{}`", literal)),
+            },
+            ParserError::ExpectedIdent(_) => Some("add a name here".to_owned()),
+            ParserError::NestingTooDeep(_) => {
+                Some("split the expression up using `let` bindings".to_owned())
+            }
+            ParserError::Lexer(err) => err.suggestion(),
+            _ => None,
+        }
+    }
+}
+
 impl Display for ParserError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -60,80 +154,391 @@ impl Display for ParserError {
             ParserError::UnhandledExpression(tok) => {
                 write!(f, "No handler for expression: {:?}", tok)
             }
+            ParserError::Lexer(err) => write!(f, "{}", err),
+            ParserError::NestingTooDeep(max) => {
+                write!(f, "Expression nests more than {} levels deep", max)
+            }
+            ParserError::Disabled(extension) => {
+                write!(f, "{} is not allowed in this dialect", extension)
+            }
+        }
+    }
+}
+
+/// Something the parser accepts but which is probably a mistake. Warnings
+/// never fail a parse.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParserWarning {
+    /// An expression statement which isn't the last in its block, so its
+    /// value is thrown away, and which can't do anything else.
+    NoEffect,
+    /// A `;` after an `if` or `try` statement.
+    RedundantSemicolon,
+}
+
+impl ParserWarning {
+    /// The stable code identifying this kind of warning, for
+    /// `monkey explain`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParserWarning::NoEffect => "W0001",
+            ParserWarning::RedundantSemicolon => "W0002",
+        }
+    }
+
+    pub fn suggestion(&self) -> &'static str {
+        match self {
+            ParserWarning::NoEffect => "remove the statement, or bind its value with `let`",
+            ParserWarning::RedundantSemicolon => "remove the `;`",
+        }
+    }
+}
+
+impl Display for ParserWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ParserWarning::NoEffect => "statement has no effect",
+            ParserWarning::RedundantSemicolon => "semicolon after block is redundant",
+        })
+    }
+}
+
+/// Syntax this parser accepts beyond the language in the book, each of
+/// which `ParserOptions` can turn off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extension {
+    Ranges,
+    Pipes,
+    Power,
+    Bitwise,
+    TryCatch,
+    Constants,
+    Destructuring,
+    Interpolation,
+    MemberAccess,
+    Slices,
+}
+
+impl Extension {
+    /// The extension a token of `kind` starts, if it only appears in one.
+    fn of(kind: TokenKind) -> Option<Extension> {
+        let extension = match kind {
+            TokenKind::DotDot | TokenKind::DotDotEq => Extension::Ranges,
+            TokenKind::Pipe => Extension::Pipes,
+            TokenKind::Power => Extension::Power,
+            TokenKind::ShiftLeft
+            | TokenKind::ShiftRight
+            | TokenKind::Ampersand
+            | TokenKind::Bar
+            | TokenKind::Caret
+            | TokenKind::Tilde => Extension::Bitwise,
+            TokenKind::Try => Extension::TryCatch,
+            TokenKind::Const => Extension::Constants,
+            TokenKind::InterpolationStart => Extension::Interpolation,
+            TokenKind::Dot => Extension::MemberAccess,
+            _ => return None,
+        };
+        Some(extension)
+    }
+}
+
+impl Display for Extension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Extension::Ranges => "a range",
+            Extension::Pipes => "`|>`",
+            Extension::Power => "`**`",
+            Extension::Bitwise => "a bitwise operator",
+            Extension::TryCatch => "`try`",
+            Extension::Constants => "`const`",
+            Extension::Destructuring => "a destructuring `let`",
+            Extension::Interpolation => "string interpolation",
+            Extension::MemberAccess => "`.` access",
+            Extension::Slices => "a slice",
+        })
+    }
+}
+
+/// Dialect choices for the parser, so an embedder can hold scripts to a
+/// stricter language than the default. Lexer-level extensions are in
+/// `LanguageFeatures`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// Require a `;` after every expression statement, other than the last
+    /// in a block and those ending in a block themselves.
+    pub require_semicolons: bool,
+    /// Accept a comma after the last argument of a call or element of an
+    /// array literal.
+    pub trailing_commas: bool,
+    /// Accept `start..end` and `start..=end`.
+    pub ranges: bool,
+    /// Accept `value |> function`.
+    pub pipes: bool,
+    /// Accept `a ** b`.
+    pub power: bool,
+    /// Accept `&`, `|`, `^`, `~`, `<<` and `>>`.
+    pub bitwise: bool,
+    /// Accept `try { ... } catch (err) { ... }`.
+    pub try_catch: bool,
+    /// Accept `const` bindings.
+    pub constants: bool,
+    /// Accept array and hash patterns in `let`, and `let a, b = pair`.
+    pub destructuring: bool,
+    /// Accept `${...}` in strings.
+    pub interpolation: bool,
+    /// Accept `value.name`.
+    pub member_access: bool,
+    /// Accept `value[start:end]`.
+    pub slices: bool,
+    /// How deeply expressions and blocks may nest before the parser gives
+    /// up, rather than risk overflowing the stack. 100 by default.
+    pub max_nesting_depth: usize,
+}
+
+impl ParserOptions {
+    /// The language as described in the book: every `Extension` is turned
+    /// off. Comments, raw strings and `return a, b` are still accepted, as
+    /// they don't change what any of the book's programs mean.
+    pub fn book() -> Self {
+        ParserOptions {
+            ranges: false,
+            pipes: false,
+            power: false,
+            bitwise: false,
+            try_catch: false,
+            constants: false,
+            destructuring: false,
+            interpolation: false,
+            member_access: false,
+            slices: false,
+            ..ParserOptions::default()
+        }
+    }
+
+    /// Check whether `extension` is turned on.
+    pub fn allows(&self, extension: Extension) -> bool {
+        match extension {
+            Extension::Ranges => self.ranges,
+            Extension::Pipes => self.pipes,
+            Extension::Power => self.power,
+            Extension::Bitwise => self.bitwise,
+            Extension::TryCatch => self.try_catch,
+            Extension::Constants => self.constants,
+            Extension::Destructuring => self.destructuring,
+            Extension::Interpolation => self.interpolation,
+            Extension::MemberAccess => self.member_access,
+            Extension::Slices => self.slices,
+        }
+    }
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            require_semicolons: false,
+            trailing_commas: false,
+            ranges: true,
+            pipes: true,
+            power: true,
+            bitwise: true,
+            try_catch: true,
+            constants: true,
+            destructuring: true,
+            interpolation: true,
+            member_access: true,
+            slices: true,
+            max_nesting_depth: 100,
+        }
+    }
+}
+
+/// A program with syntax errors, along with as much of it as parsed.
+#[derive(Debug)]
+pub struct ParseFailure {
+    pub program: Program,
+    pub errors: Vec<ParserError>,
+}
+
+impl Display for ParseFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, err) in self.errors.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", err)?;
         }
+        Ok(())
     }
 }
 
-pub struct Parser<'a> {
-    lexer: Lexer<'a>,
+pub struct Parser<S> {
+    tokens: S,
     errors: Vec<ParserError>,
+    error_spans: Vec<Span>,
+    warnings: Vec<ParserWarning>,
+    warning_spans: Vec<Span>,
     cur_token: Token,
     peek_token: Token,
+    /// Doc comments before the current and peek tokens.
+    cur_doc: Option<String>,
+    peek_doc: Option<String>,
+    /// Whether the expression parsed last was wholly in parentheses, so a
+    /// pipe can tell `x |> (f(1))` from `x |> f(1)`.
+    grouped: bool,
+    depth: usize,
+    options: ParserOptions,
+}
+
+impl<'a> Parser<Lexer<'a>> {
+    pub fn from_input(input: &'a str) -> Self {
+        Parser::new(Lexer::new(input))
+    }
+
+    /// Parse from `reader` as it is read, as with `Lexer::from_reader`.
+    pub fn from_reader<R: BufRead + 'a>(reader: R) -> Self {
+        Parser::new(Lexer::from_reader(reader))
+    }
 }
 
-impl<'a> Parser<'a> {
-    pub fn new(mut lexer: Lexer<'a>) -> Self {
-        let cur_token = lexer.next_token();
-        let peek_token = lexer.next_token();
-        Parser {
-            lexer,
+impl<S: TokenStream> Parser<S> {
+    /// Parse the tokens from `tokens`, usually a `Lexer`.
+    pub fn new(tokens: S) -> Self {
+        let mut parser = Parser {
+            tokens,
             errors: vec![],
-            cur_token,
-            peek_token,
-        }
+            error_spans: vec![],
+            warnings: vec![],
+            warning_spans: vec![],
+            cur_token: Token::from(TokenKind::EOF),
+            peek_token: Token::from(TokenKind::EOF),
+            cur_doc: None,
+            peek_doc: None,
+            grouped: false,
+            depth: 0,
+            options: ParserOptions::default(),
+        };
+        // Fill both slots the way every later token is read, so illegal
+        // characters at the very start are reported and skipped too.
+        parser.next_token();
+        parser.next_token();
+        parser
     }
 
-    pub fn from_input(input: &'a str) -> Self {
-        Parser::new(Lexer::new(input))
+    /// Parse the dialect described by `options`.
+    pub fn with_options(mut self, options: ParserOptions) -> Self {
+        self.options = options;
+        self
     }
 
     pub fn errors(&self) -> &[ParserError] {
         &self.errors
     }
 
-    pub fn peek_error(&mut self, expected: &Token) -> ParserError {
+    /// The span of the token each of `errors()` was reported at, in the
+    /// same order.
+    pub fn error_spans(&self) -> &[Span] {
+        &self.error_spans
+    }
+
+    pub fn warnings(&self) -> &[ParserWarning] {
+        &self.warnings
+    }
+
+    /// The span each of `warnings()` is about, in the same order.
+    pub fn warning_spans(&self) -> &[Span] {
+        &self.warning_spans
+    }
+
+    /// The parser's errors and then its warnings as diagnostics, at the
+    /// span each was reported.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let errors = self
+            .errors
+            .iter()
+            .zip(&self.error_spans)
+            .map(|(err, span)| {
+                let diagnostic = Diagnostic::error(err.to_string())
+                    .with_code(err.code())
+                    .with_span(*span);
+                match err.suggestion() {
+                    Some(suggestion) => diagnostic.with_suggestion(suggestion),
+                    None => diagnostic,
+                }
+            });
+        let warnings = self
+            .warnings
+            .iter()
+            .zip(&self.warning_spans)
+            .map(|(warning, span)| {
+                Diagnostic::warning(warning.to_string())
+                    .with_code(warning.code())
+                    .with_span(*span)
+                    .with_suggestion(warning.suggestion().to_owned())
+            });
+        errors.chain(warnings).collect()
+    }
+
+    fn push_warning(&mut self, warning: ParserWarning, span: Span) {
+        self.warnings.push(warning);
+        self.warning_spans.push(span);
+    }
+
+    fn push_error(&mut self, err: ParserError) {
+        let span = match err {
+            ParserError::ExpectedToken { .. } | ParserError::ExpectedIdent(_) => {
+                self.peek_token.span
+            }
+            _ => self.cur_token.span,
+        };
+        self.errors.push(err);
+        self.error_spans.push(span);
+    }
+
+    pub fn peek_error(&mut self, expected: TokenKind) -> ParserError {
         ParserError::ExpectedToken {
-            expected: expected.clone(),
+            expected,
             saw: self.peek_token.clone(),
         }
     }
 
     pub fn next_token(&mut self) {
         std::mem::swap(&mut self.cur_token, &mut self.peek_token);
-        self.peek_token = self.lexer.next_token();
+        let mut peek_token = self.tokens.next_token();
+        // Illegal characters are reported here and skipped, rather than left
+        // to surface as an error from whatever expected a real token.
+        while let Some(err) = self.tokens.take_illegal() {
+            self.errors.push(ParserError::Lexer(err));
+            self.error_spans.push(peek_token.span);
+            peek_token = self.tokens.next_token();
+        }
+        self.peek_token = peek_token;
+        self.cur_doc = self.peek_doc.take();
+        self.peek_doc = self.tokens.take_doc();
     }
 
-    pub fn current_token_is(&self, tok: &Token) -> bool {
-        match (&tok, &self.cur_token) {
-            (Token::Ident(_), Token::Ident(_)) => true,
-            (Token::Int(_), Token::Int(_)) => true,
-            _ => tok == &self.cur_token,
-        }
+    pub fn current_token_is(&self, kind: TokenKind) -> bool {
+        self.cur_token.kind == kind
     }
 
-    pub fn peek_token_is(&self, tok: &Token) -> bool {
-        match (&tok, &self.peek_token) {
-            (Token::Ident(_), Token::Ident(_)) => true,
-            (Token::Int(_), Token::Int(_)) => true,
-            _ => tok == &self.peek_token,
-        }
+    pub fn peek_token_is(&self, kind: TokenKind) -> bool {
+        self.peek_token.kind == kind
     }
 
-    pub fn expect_peek(&mut self, expected: &Token) -> ParserResult<()> {
+    pub fn expect_peek(&mut self, expected: TokenKind) -> ParserResult<()> {
         if self.peek_token_is(expected) {
             self.next_token();
             return Ok(());
         }
-        Err(ParserError::ExpectedToken {
-            expected: expected.clone(),
-            saw: self.peek_token.clone(),
-        })
+        Err(self.peek_error(expected))
     }
 
+    /// Advance past an identifier, moving its name out of the token rather
+    /// than copying it.
     pub fn expect_ident(&mut self) -> ParserResult<Identifier> {
-        match &self.peek_token {
-            Token::Ident(name) => {
-                let ident = Identifier::new(name);
+        match self.peek_token.kind {
+            TokenKind::Ident => {
+                let name = mem::take(&mut self.peek_token.literal);
+                let ident = Identifier(name, self.peek_token.span);
                 self.next_token();
                 Ok(ident)
             }
@@ -141,71 +546,247 @@ impl<'a> Parser<'a> {
         }
     }
 
-    pub fn parse_program(&mut self) -> Option<Program> {
+    /// Parse the rest of the input as a program. The errors stay on the
+    /// parser as well, for `diagnostics()`.
+    pub fn parse_program(&mut self) -> Result<Program, ParseFailure> {
         let mut program = Program::default();
 
-        while self.cur_token != Token::EOF {
-            match self.parse_statement() {
-                Ok(stmt) => program.statements.push(stmt),
-                Err(err) => self.errors.push(err),
+        while !self.at_eof() {
+            if let Some((stmt, _)) = self.parse_top_level_statement() {
+                program.statements.push(stmt);
             }
-            self.next_token();
         }
-        Some(program)
+        self.finish();
+        if self.errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(ParseFailure {
+                program,
+                errors: self.errors.clone(),
+            })
+        }
+    }
+
+    /// Parse the rest of the input as a program, leaving any errors to be
+    /// read from `errors()`. Never returns `None`.
+    #[deprecated(note = "use `parse_program`, which returns the errors")]
+    pub fn parse_program_option(&mut self) -> Option<Program> {
+        Some(
+            self.parse_program()
+                .unwrap_or_else(|failure| failure.program),
+        )
+    }
+
+    pub(crate) fn at_eof(&self) -> bool {
+        self.cur_token == TokenKind::EOF
+    }
+
+    /// Parse the statement at the current token and move past it, returning
+    /// it with the span from its first token to its last. An error is
+    /// recorded instead of returned.
+    pub(crate) fn parse_top_level_statement(&mut self) -> Option<(Statement, Span)> {
+        let start = self.cur_token.span.start;
+        let stmt = match self.parse_statement() {
+            Ok(stmt) => Some((stmt, Span::new(start, self.cur_token.span.end))),
+            Err(err) => {
+                self.push_error(err);
+                None
+            }
+        };
+        self.next_token();
+        stmt
+    }
+
+    /// Record the error which ended the token stream early, if any.
+    pub(crate) fn finish(&mut self) {
+        if let Some(err) = self.tokens.error() {
+            let err = ParserError::Lexer(err.clone());
+            self.push_error(err);
+        }
+    }
+
+    /// The spans of the current and peek tokens.
+    pub(crate) fn lookahead(&self) -> (Span, Span) {
+        (self.cur_token.span, self.peek_token.span)
+    }
+
+    pub(crate) fn tokens(&self) -> &S {
+        &self.tokens
     }
 
     pub fn parse_statement(&mut self) -> ParserResult<Statement> {
-        match self.cur_token {
-            Token::Let => self.parse_let_statement(),
-            Token::Return => self.parse_return_statement(),
+        match self.cur_token.kind {
+            TokenKind::Let | TokenKind::Const => self.parse_let_statement(),
+            TokenKind::Return => self.parse_return_statement(),
             _ => self.parse_expression_statement(),
         }
     }
 
     pub fn parse_let_statement(&mut self) -> ParserResult<Statement> {
         let token = self.cur_token.clone();
-        let name = self.expect_ident()?;
-        self.expect_peek(&Token::Assign)?;
+        if token == TokenKind::Const {
+            self.require(Extension::Constants)?;
+        }
+        let doc = self.cur_doc.take();
+        let patterns = [TokenKind::LBracket, TokenKind::LBrace];
+        self.require_at_peek(&patterns, Extension::Destructuring)?;
+        let mut pattern = self.expect_pattern()?;
+        // `let a, b = pair;` is sugar for `let [a, b] = pair;`.
+        self.require_at_peek(&[TokenKind::Comma], Extension::Destructuring)?;
+        if self.peek_token_is(TokenKind::Comma) {
+            let mut elements = vec![pattern];
+            while self.peek_token_is(TokenKind::Comma) {
+                self.next_token();
+                elements.push(self.expect_pattern()?);
+            }
+            pattern = Pattern::Array(elements);
+        }
+        self.expect_peek(TokenKind::Assign)?;
         self.next_token();
         let value = self.parse_expression(Precedence::Lowest)?;
-        if self.peek_token_is(&Token::Semicolon) {
+        if self.peek_token_is(TokenKind::Semicolon) {
+            self.next_token();
+        }
+        Ok(match pattern {
+            Pattern::Identifier(name) => Statement::Let {
+                token,
+                name,
+                value,
+                doc,
+            },
+            pattern => Statement::Destructure {
+                token,
+                pattern,
+                value,
+            },
+        })
+    }
+
+    /// Advance past a name, or an array or hash pattern of them.
+    fn expect_pattern(&mut self) -> ParserResult<Pattern> {
+        match self.peek_token.kind {
+            TokenKind::LBracket => {
+                self.next_token();
+                self.nested(|parser| {
+                    let elements =
+                        parser.parse_pattern_list(TokenKind::RBracket, Parser::expect_pattern)?;
+                    Ok(Pattern::Array(elements))
+                })
+            }
+            TokenKind::LBrace => {
+                self.next_token();
+                let names = self.parse_pattern_list(TokenKind::RBrace, Parser::expect_ident)?;
+                Ok(Pattern::Hash(names))
+            }
+            _ => Ok(Pattern::Identifier(self.expect_ident()?)),
+        }
+    }
+
+    /// Parse comma-separated `item`s up to the closing `end` token.
+    fn parse_pattern_list<T>(
+        &mut self,
+        end: TokenKind,
+        item: fn(&mut Self) -> ParserResult<T>,
+    ) -> ParserResult<Vec<T>> {
+        let mut items = vec![];
+        if self.peek_token_is(end) {
+            self.next_token();
+            return Ok(items);
+        }
+        items.push(item(self)?);
+        while self.peek_token_is(TokenKind::Comma) {
+            self.next_token();
+            items.push(item(self)?);
+        }
+        self.expect_peek(end)?;
+        Ok(items)
+    }
+
+    /// Fail with `Disabled` if the options turn `extension` off. The error
+    /// is reported at the current token.
+    fn require(&self, extension: Extension) -> ParserResult<()> {
+        if self.options.allows(extension) {
+            Ok(())
+        } else {
+            Err(ParserError::Disabled(extension))
+        }
+    }
+
+    /// Advance to the peek token and fail with `Disabled` if it is one of
+    /// `kinds` and the options turn `extension` off.
+    fn require_at_peek(&mut self, kinds: &[TokenKind], extension: Extension) -> ParserResult<()> {
+        if kinds.contains(&self.peek_token.kind) && !self.options.allows(extension) {
             self.next_token();
+            return Err(ParserError::Disabled(extension));
         }
-        Ok(Statement::Let { token, name, value })
+        Ok(())
     }
 
     pub fn cur_precedence(&self) -> Precedence {
-        Precedence::for_token(&self.cur_token)
+        Precedence::for_kind(self.cur_token.kind)
     }
 
     pub fn peek_precedence(&self) -> Precedence {
-        Precedence::for_token(&self.peek_token)
+        Precedence::for_kind(self.peek_token.kind)
     }
 
     pub fn parse_return_statement(&mut self) -> ParserResult<Statement> {
         let token = self.cur_token.clone();
-        let stmt = Statement::Return {
-            token,
-            expr: Expression::Nothing,
+        let expr = match self.peek_token.kind {
+            TokenKind::Semicolon | TokenKind::RBrace | TokenKind::EOF => Expression::Nothing,
+            _ => {
+                self.next_token();
+                let expr = self.parse_expression(Precedence::Lowest)?;
+                // `return a, b;` is sugar for `return [a, b];`.
+                if self.peek_token_is(TokenKind::Comma) {
+                    let mut elements = vec![expr];
+                    while self.peek_token_is(TokenKind::Comma) {
+                        self.next_token();
+                        self.next_token();
+                        elements.push(self.parse_expression(Precedence::Lowest)?);
+                    }
+                    Expression::ArrayLiteral(elements)
+                } else {
+                    expr
+                }
+            }
         };
-        self.next_token();
-        while !self.current_token_is(&Token::Semicolon) {
+        if self.peek_token_is(TokenKind::Semicolon) {
             self.next_token();
         }
-        Ok(stmt)
+        Ok(Statement::Return { token, expr })
     }
 
     pub fn parse_expression_statement(&mut self) -> ParserResult<Statement> {
+        let token = self.cur_token.clone();
+        let start = self.cur_token.span.start;
         let expr = self.parse_expression(Precedence::Lowest)?;
-        let stmt = Statement::Expression {
-            token: self.cur_token.clone(),
-            expr,
-        };
+        let span = Span::new(start, self.cur_token.span.end);
 
-        if self.peek_token_is(&Token::Semicolon) {
+        let ends_in_block = matches!(expr, Expression::If { .. } | Expression::Try { .. });
+        if self.peek_token_is(TokenKind::Semicolon) {
             self.next_token();
+            if ends_in_block {
+                self.push_warning(ParserWarning::RedundantSemicolon, self.cur_token.span);
+            }
+        } else if self.options.require_semicolons
+            && !ends_in_block
+            && !self.peek_token_is(TokenKind::RBrace)
+        {
+            return Err(self.peek_error(TokenKind::Semicolon));
+        }
+        // The last statement in a block is its value.
+        let last = self.peek_token_is(TokenKind::RBrace) || self.peek_token_is(TokenKind::EOF);
+        // A statement cut short by an illegal character isn't worth a
+        // warning on top of the error.
+        let cut_short = self
+            .error_spans
+            .last()
+            .is_some_and(|error| error.start >= start);
+        if !last && !has_effect(&expr) && !cut_short {
+            self.push_warning(ParserWarning::NoEffect, span);
         }
-        Ok(stmt)
+        Ok(Statement::Expression { token, expr })
     }
 
     pub fn parse_int_expression(&self, value_str: &str) -> ParserResult<Expression> {
@@ -229,7 +810,13 @@ impl<'a> Parser<'a> {
         let operator = self.cur_token.clone();
         let precedence = self.cur_precedence();
         self.next_token();
-        let right = self.parse_expression(precedence)?;
+        // A right-associative operator lets its right operand take another
+        // of the same operator, by parsing it one tier lower.
+        let right = if Precedence::is_right_associative(operator.kind) {
+            self.parse_expression(precedence.lower())?
+        } else {
+            self.parse_expression(precedence)?
+        };
         Ok(Expression::Infix {
             operator,
             left: Box::new(left),
@@ -237,20 +824,173 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Parse `[index]` or `[start:end]` after `left`. Either bound of a
+    /// slice may be left out, as in `[:end]`, `[start:]` or `[:]`.
+    pub fn parse_index_expression(&mut self, left: Expression) -> ParserResult<Expression> {
+        self.next_token();
+        let start = if self.current_token_is(TokenKind::Colon) {
+            None
+        } else {
+            let index = self.parse_expression(Precedence::Lowest)?;
+            if !self.peek_token_is(TokenKind::Colon) {
+                self.expect_peek(TokenKind::RBracket)?;
+                return Ok(Expression::Index {
+                    left: Box::new(left),
+                    index: Box::new(index),
+                });
+            }
+            self.next_token();
+            Some(Box::new(index))
+        };
+        self.require(Extension::Slices)?;
+        let end = if self.peek_token_is(TokenKind::RBracket) {
+            None
+        } else {
+            self.next_token();
+            Some(Box::new(self.parse_expression(Precedence::Lowest)?))
+        };
+        self.expect_peek(TokenKind::RBracket)?;
+        Ok(Expression::Slice {
+            left: Box::new(left),
+            start,
+            end,
+        })
+    }
+
+    /// Parse the rest of an interpolated string starting with `text`, as
+    /// alternating expressions and the text between them.
+    pub fn parse_interpolated_string(&mut self, text: String) -> ParserResult<Expression> {
+        let mut parts = vec![];
+        let mut text = text;
+        loop {
+            if !text.is_empty() {
+                parts.push(StringPart::Text(text));
+            }
+            self.next_token();
+            parts.push(StringPart::Expr(self.parse_expression(Precedence::Lowest)?));
+            let closed = match self.peek_token.kind {
+                TokenKind::InterpolationMid => false,
+                TokenKind::InterpolationEnd => true,
+                _ => return Err(self.peek_error(TokenKind::InterpolationEnd)),
+            };
+            text = mem::take(&mut self.peek_token.literal);
+            self.next_token();
+            if closed {
+                if !text.is_empty() {
+                    parts.push(StringPart::Text(text));
+                }
+                return Ok(Expression::Interpolated(parts));
+            }
+        }
+    }
+
+    /// Parse `..end` or `..=end` after `start`.
+    pub fn parse_range_expression(&mut self, start: Expression) -> ParserResult<Expression> {
+        let inclusive = self.cur_token == TokenKind::DotDotEq;
+        self.next_token();
+        let end = self.parse_expression(Precedence::Range)?;
+        Ok(Expression::Range {
+            start: Box::new(start),
+            end: Box::new(end),
+            inclusive,
+        })
+    }
+
+    /// Parse `(arguments...)` after `function`.
+    pub fn parse_call_expression(&mut self, function: Expression) -> ParserResult<Expression> {
+        let arguments = self.parse_expression_list(TokenKind::RParen)?;
+        Ok(Expression::Call {
+            function: Box::new(function),
+            arguments,
+        })
+    }
+
+    /// Parse `|> right` after `left`. This is sugar for a call: `x |> f` is
+    /// `f(x)`, and a call on the right gets `left` as its first argument,
+    /// so `x |> g(1)` is `g(x, 1)`. A call in parentheses is called as it
+    /// is, so `x |> (g(1))` is `g(1)(x)`.
+    pub fn parse_pipe_expression(&mut self, left: Expression) -> ParserResult<Expression> {
+        self.next_token();
+        let mut right = self.parse_expression(Precedence::Pipe)?;
+        // Only a bare call takes the value as its first argument. One in
+        // parentheses is evaluated first, and its result called with it.
+        if let (Expression::Call { arguments, .. }, false) = (&mut right, self.grouped) {
+            arguments.insert(0, left);
+            return Ok(right);
+        }
+        Ok(Expression::Call {
+            function: Box::new(right),
+            arguments: vec![left],
+        })
+    }
+
+    /// Parse `.name` after `left`, which is sugar for `left["name"]`.
+    pub fn parse_member_expression(&mut self, left: Expression) -> ParserResult<Expression> {
+        let Identifier(name, _) = self.expect_ident()?;
+        Ok(Expression::Index {
+            left: Box::new(left),
+            index: Box::new(Expression::StringLiteral(name)),
+        })
+    }
+
+    pub fn parse_array_literal(&mut self) -> ParserResult<Expression> {
+        let elements = self.parse_expression_list(TokenKind::RBracket)?;
+        Ok(Expression::ArrayLiteral(elements))
+    }
+
+    /// Parse comma-separated expressions up to the closing `end` token.
+    fn parse_expression_list(&mut self, end: TokenKind) -> ParserResult<Vec<Expression>> {
+        let mut list = vec![];
+        if self.peek_token_is(end) {
+            self.next_token();
+            return Ok(list);
+        }
+        self.next_token();
+        list.push(self.parse_expression(Precedence::Lowest)?);
+        while self.peek_token_is(TokenKind::Comma) {
+            self.next_token();
+            if self.options.trailing_commas && self.peek_token_is(end) {
+                break;
+            }
+            self.next_token();
+            list.push(self.parse_expression(Precedence::Lowest)?);
+        }
+        self.expect_peek(end)?;
+        Ok(list)
+    }
+
     pub fn parse_boolean_expression(&self, is_true: bool) -> ParserResult<Expression> {
         Ok(Expression::Boolean(is_true))
     }
 
     pub fn parse_grouped_expression(&mut self) -> ParserResult<Expression> {
         self.next_token();
-        let expr = self.parse_expression(Precedence::Lowest);
-        self.expect_peek(&Token::RParen).and(expr)
+        let expr = self.parse_expression(Precedence::Lowest)?;
+        self.expect_peek(TokenKind::RParen)?;
+        Ok(expr)
+    }
+
+    /// Run `parse` one nesting level deeper, failing once the levels exceed
+    /// `max_nesting_depth`.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> ParserResult<T>) -> ParserResult<T> {
+        let max = self.options.max_nesting_depth;
+        if self.depth >= max {
+            return Err(ParserError::NestingTooDeep(max));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
     }
 
     pub fn parse_block_statement(&mut self) -> ParserResult<BlockStatement> {
+        self.nested(Parser::parse_block_statement_inner)
+    }
+
+    fn parse_block_statement_inner(&mut self) -> ParserResult<BlockStatement> {
         let mut block = BlockStatement::new(self.cur_token.clone());
         self.next_token();
-        while !self.current_token_is(&Token::RBrace) && !self.current_token_is(&Token::EOF) {
+        while !self.current_token_is(TokenKind::RBrace) && !self.current_token_is(TokenKind::EOF) {
             let stmt = self.parse_statement()?;
             block.statements.push(stmt);
             self.next_token();
@@ -259,16 +999,28 @@ impl<'a> Parser<'a> {
     }
 
     pub fn parse_if_expression(&mut self) -> ParserResult<Expression> {
-        self.expect_peek(&Token::LParen)?;
+        self.expect_peek(TokenKind::LParen)?;
 
         let condition = self.parse_expression(Precedence::Lowest)?;
-        self.expect_peek(&Token::LBrace)?;
+        self.expect_peek(TokenKind::LBrace)?;
         let consequence = self.parse_block_statement()?;
-        let alternative = match self.peek_token {
-            Token::Else => {
+        let alternative = match self.peek_token.kind {
+            TokenKind::Else => {
                 self.next_token();
-                self.expect_peek(&Token::LBrace)?;
-                Some(self.parse_block_statement()?)
+                if self.peek_token_is(TokenKind::If) {
+                    // `else if` is sugar for an alternative block holding a
+                    // single if expression.
+                    self.next_token();
+                    let mut block = BlockStatement::new(self.cur_token.clone());
+                    block.statements.push(Statement::Expression {
+                        token: self.cur_token.clone(),
+                        expr: self.parse_if_expression()?,
+                    });
+                    Some(block)
+                } else {
+                    self.expect_peek(TokenKind::LBrace)?;
+                    Some(self.parse_block_statement()?)
+                }
             }
             _ => None,
         };
@@ -279,55 +1031,188 @@ impl<'a> Parser<'a> {
         })
     }
 
+    pub fn parse_try_expression(&mut self) -> ParserResult<Expression> {
+        self.expect_peek(TokenKind::LBrace)?;
+        let body = self.parse_block_statement()?;
+        self.expect_peek(TokenKind::Catch)?;
+        self.expect_peek(TokenKind::LParen)?;
+        let binding = self.expect_ident()?;
+        self.expect_peek(TokenKind::RParen)?;
+        self.expect_peek(TokenKind::LBrace)?;
+        let handler = self.parse_block_statement()?;
+        Ok(Expression::Try {
+            body,
+            binding,
+            handler,
+        })
+    }
+
+    pub fn parse_function_literal(&mut self) -> ParserResult<Expression> {
+        let doc = self.cur_doc.take();
+        self.expect_peek(TokenKind::LParen)?;
+        let parameters = self.parse_function_parameters()?;
+        self.expect_peek(TokenKind::LBrace)?;
+        let body = self.parse_block_statement()?;
+        Ok(Expression::Function {
+            parameters,
+            body,
+            doc,
+        })
+    }
+
+    fn parse_function_parameters(&mut self) -> ParserResult<Vec<Identifier>> {
+        let mut parameters = vec![];
+        if self.peek_token_is(TokenKind::RParen) {
+            self.next_token();
+            return Ok(parameters);
+        }
+        parameters.push(self.expect_ident()?);
+        while self.peek_token_is(TokenKind::Comma) {
+            self.next_token();
+            parameters.push(self.expect_ident()?);
+        }
+        self.expect_peek(TokenKind::RParen)?;
+        Ok(parameters)
+    }
+
     pub fn parse_expression(&mut self, precedence: Precedence) -> ParserResult<Expression> {
-        let mut left = match &self.cur_token {
-            Token::Ident(name) => Expression::Identifier(Identifier::new(name)),
-            Token::Int(value_str) => self.parse_int_expression(value_str)?,
-            Token::Bang => self.parse_prefix_expression()?,
-            Token::Minus => self.parse_prefix_expression()?,
-            Token::Plus => self.parse_prefix_expression()?,
-            Token::True => self.parse_boolean_expression(true)?,
-            Token::False => self.parse_boolean_expression(false)?,
-            Token::LParen => self.parse_grouped_expression()?,
-            Token::If => self.parse_if_expression()?,
+        self.nested(|parser| parser.parse_expression_inner(precedence))
+    }
+
+    fn parse_expression_inner(&mut self, precedence: Precedence) -> ParserResult<Expression> {
+        // Literal payloads are moved out of the current token, which is never
+        // read again once its expression has been built.
+        if let Some(extension) = Extension::of(self.cur_token.kind) {
+            self.require(extension)?;
+        }
+        let mut grouped = self.current_token_is(TokenKind::LParen);
+        let mut left = match self.cur_token.kind {
+            TokenKind::Ident => {
+                let name = mem::take(&mut self.cur_token.literal);
+                Expression::Identifier(Identifier(name, self.cur_token.span))
+            }
+            TokenKind::Int => {
+                let value_str = mem::take(&mut self.cur_token.literal);
+                self.parse_int_expression(&value_str)?
+            }
+            TokenKind::String => Expression::StringLiteral(mem::take(&mut self.cur_token.literal)),
+            TokenKind::InterpolationStart => {
+                let text = mem::take(&mut self.cur_token.literal);
+                self.parse_interpolated_string(text)?
+            }
+            TokenKind::Bang => self.parse_prefix_expression()?,
+            TokenKind::Minus => self.parse_prefix_expression()?,
+            TokenKind::Plus => self.parse_prefix_expression()?,
+            TokenKind::Tilde => self.parse_prefix_expression()?,
+            TokenKind::True => self.parse_boolean_expression(true)?,
+            TokenKind::False => self.parse_boolean_expression(false)?,
+            TokenKind::LParen => self.parse_grouped_expression()?,
+            TokenKind::LBracket => self.parse_array_literal()?,
+            TokenKind::If => self.parse_if_expression()?,
+            TokenKind::Try => self.parse_try_expression()?,
+            TokenKind::Function => self.parse_function_literal()?,
             _ => return Err(ParserError::UnhandledPrefix(self.cur_token.clone())),
         };
 
-        while !self.peek_token_is(&Token::Semicolon) && precedence < self.peek_precedence() {
-            left = match &self.peek_token {
-                Token::Plus
-                | Token::Minus
-                | Token::Asterisk
-                | Token::Slash
-                | Token::Gt
-                | Token::Lt
-                | Token::Eq
-                | Token::NotEq => {
+        while !self.peek_token_is(TokenKind::Semicolon) && precedence < self.peek_precedence() {
+            if let Some(extension) = Extension::of(self.peek_token.kind) {
+                self.require_at_peek(&[self.peek_token.kind], extension)?;
+            }
+            left = match self.peek_token.kind {
+                TokenKind::Plus
+                | TokenKind::Minus
+                | TokenKind::Asterisk
+                | TokenKind::Slash
+                | TokenKind::Power
+                | TokenKind::Gt
+                | TokenKind::Lt
+                | TokenKind::ShiftLeft
+                | TokenKind::ShiftRight
+                | TokenKind::Ampersand
+                | TokenKind::Bar
+                | TokenKind::Caret
+                | TokenKind::Eq
+                | TokenKind::NotEq => {
                     self.next_token();
                     self.parse_infix_expression(left)?
                 }
-                _ => return Ok(left),
+                TokenKind::LBracket => {
+                    self.next_token();
+                    self.parse_index_expression(left)?
+                }
+                TokenKind::Dot => {
+                    self.next_token();
+                    self.parse_member_expression(left)?
+                }
+                TokenKind::LParen => {
+                    self.next_token();
+                    self.parse_call_expression(left)?
+                }
+                TokenKind::Pipe => {
+                    self.next_token();
+                    self.parse_pipe_expression(left)?
+                }
+                TokenKind::DotDot | TokenKind::DotDotEq => {
+                    self.next_token();
+                    self.parse_range_expression(left)?
+                }
+                _ => break,
             };
+            grouped = false;
         }
+        self.grouped = grouped;
         Ok(left)
     }
 }
 
+/// Whether evaluating `expr` could do anything besides produce a value.
+/// Calls might, as might anything holding a block.
+fn has_effect(expr: &Expression) -> bool {
+    match expr {
+        Expression::Identifier(_)
+        | Expression::IntegerLiteral(_)
+        | Expression::StringLiteral(_)
+        | Expression::Boolean(_)
+        | Expression::Function { .. }
+        | Expression::Nothing => false,
+        Expression::Interpolated(parts) => parts.iter().any(|part| match part {
+            StringPart::Text(_) => false,
+            StringPart::Expr(expr) => has_effect(expr),
+        }),
+        Expression::ArrayLiteral(items) => items.iter().any(has_effect),
+        Expression::Prefix { right, .. } => has_effect(right),
+        Expression::Infix { left, right, .. } => has_effect(left) || has_effect(right),
+        Expression::Range { start, end, .. } => has_effect(start) || has_effect(end),
+        Expression::Index { left, index } => has_effect(left) || has_effect(index),
+        Expression::Slice { left, start, end } => {
+            has_effect(left)
+                || start.as_deref().is_some_and(has_effect)
+                || end.as_deref().is_some_and(has_effect)
+        }
+        Expression::If { .. } | Expression::Try { .. } | Expression::Call { .. } => true,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::diagnostic::Severity;
+    use crate::features::LanguageFeatures;
+    use crate::lexer::Tokens;
+    use crate::lexer::{Keywords, LexerLimits};
 
     /// Construct a parser to parser the input, returning the parser and parsed
     /// Program object.
-    fn parser_for_input(input: &str) -> (Parser, Program) {
+    fn parser_for_input(input: &str) -> (Parser<Lexer<'_>>, Program) {
         let mut parser = Parser::from_input(input);
-        let program = parser.parse_program();
-        assert!(program.is_some(), "parse_program() returned None");
-        (parser, program.unwrap())
+        let program = parser
+            .parse_program()
+            .unwrap_or_else(|failure| failure.program);
+        (parser, program)
     }
 
     /// Assert that a Parser contains no errors.
-    fn assert_no_parser_errors(parser: &Parser) {
+    fn assert_no_parser_errors<S: TokenStream>(parser: &Parser<S>) {
         let errors = parser.errors();
         assert!(
             errors.is_empty(),
@@ -338,7 +1223,7 @@ mod test {
     }
 
     /// Assert that a Parser contains no errors.
-    fn assert_parser_errors_len(parser: &Parser, count: usize) {
+    fn assert_parser_errors_len<S: TokenStream>(parser: &Parser<S>, count: usize) {
         let errors = parser.errors();
 
         assert_eq!(
@@ -371,8 +1256,7 @@ mod test {
             Statement::Expression { expr, .. } => {
                 assert_eq!(expr, expected_expr);
             }
-            _ => assert!(
-                false,
+            _ => panic!(
                 "Expected Statement::Expression {{ expr: {} }}, saw {}",
                 expected_expr, stmt
             ),
@@ -390,7 +1274,7 @@ mod test {
         let (parser, program) = parser_for_input(input);
         assert_no_parser_errors(&parser);
         assert_program_statements_len(&program, 3);
-        let expected_names = vec![
+        let expected_names = [
             ("x", Expression::IntegerLiteral(5)),
             ("y", Expression::IntegerLiteral(10)),
             ("foobar", Expression::IntegerLiteral(838383)),
@@ -399,12 +1283,14 @@ mod test {
             expected_names.iter().zip(program.statements.iter())
         {
             match stmt {
-                Statement::Let { token, name, value } => {
-                    assert_eq!(token, &Token::Let);
+                Statement::Let {
+                    token, name, value, ..
+                } => {
+                    assert_eq!(token, &Token::from(TokenKind::Let));
                     assert_eq!(name.0, *expected_identifier);
                     assert_eq!(value, expected_value);
                 }
-                _ => assert!(false, "Expected Statement::Let, got {:?}", stmt),
+                _ => panic!("Expected Statement::Let, got {:?}", stmt),
             }
         }
     }
@@ -424,7 +1310,7 @@ mod test {
         assert!(
             match &errors[0] {
                 ParserError::ExpectedToken { expected, saw } => {
-                    assert_eq!(expected, &Token::Assign);
+                    assert_eq!(*expected, TokenKind::Assign);
                     assert_eq!(saw, &Token::make_int("5"));
                     true
                 }
@@ -437,7 +1323,7 @@ mod test {
         assert!(
             match &errors[1] {
                 ParserError::ExpectedIdent(saw) => {
-                    assert_eq!(saw, &Token::Assign);
+                    assert_eq!(saw, &Token::from(TokenKind::Assign));
                     true
                 }
                 _ => false,
@@ -449,7 +1335,7 @@ mod test {
         assert!(
             match &errors[2] {
                 ParserError::UnhandledPrefix(saw) => {
-                    assert_eq!(saw, &Token::Assign);
+                    assert_eq!(saw, &Token::from(TokenKind::Assign));
                     true
                 }
                 _ => false,
@@ -472,27 +1358,270 @@ mod test {
     }
 
     #[test]
-    fn test_return_statements() {
-        let input = r#"
-        return 5;
-        return 10;
-        return 993322;
-        "#;
-
-        let (parser, program) = parser_for_input(input);
+    fn test_lexer_limit_errors() {
+        let limits = LexerLimits {
+            max_identifier_len: 4,
+            ..LexerLimits::default()
+        };
+        let mut parser = Parser::new(Lexer::with_limits("let x = 5; let longname = 6;", limits));
+        let program = parser.parse_program().unwrap_err().program;
+        assert_program_statements_len(&program, 1);
+        let errors = parser.errors();
+        assert!(
+            match errors.last() {
+                Some(ParserError::Lexer(LexerError::IdentifierTooLong { max })) => *max == 4,
+                _ => false,
+            },
+            "Expected ParserError::Lexer(IdentifierTooLong), saw {:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let (parser, _program) = parser_for_input("let = 5;\nx + ;");
+        assert_eq!(
+            parser.error_spans(),
+            &[Span::new(4, 5), Span::new(4, 5), Span::new(13, 14)]
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let (parser, _program) = parser_for_input("(1 + 2;");
+        let diagnostics = parser.diagnostics();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].code, Some("P0001"));
+        assert_eq!(diagnostics[0].span, Some(Span::new(6, 7)));
+        assert_eq!(diagnostics[0].suggestion.as_deref(), Some("insert `)`"));
+        assert_eq!(diagnostics[1].suggestion, None);
+    }
+
+    #[test]
+    fn test_warnings() {
+        let input = "let f = fn(x) { x + 1; x };\nf(1);\nif (f) { 2 };\n\"${f(1)}\";\n[1, -2];\nf";
+        let (parser, program) = parser_for_input(input);
+        assert_no_parser_errors(&parser);
+        assert_eq!(program.len(), 6);
+        assert_eq!(
+            parser.warnings(),
+            &[
+                ParserWarning::NoEffect,
+                ParserWarning::RedundantSemicolon,
+                ParserWarning::NoEffect
+            ]
+        );
+        assert_eq!(
+            parser.warning_spans(),
+            &[Span::new(16, 21), Span::new(46, 47), Span::new(59, 66)]
+        );
+
+        let diagnostics = parser.diagnostics();
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].message, "statement has no effect");
+        assert_eq!(diagnostics[1].code, Some("W0002"));
+        assert_eq!(diagnostics[1].suggestion.as_deref(), Some("remove the `;`"));
+    }
+
+    #[test]
+    fn test_illegal_character_at_start() {
+        let (parser, program) = parser_for_input("@ let x = 1;");
+        assert_eq!(program.to_string(), "let x = 1;\n");
+        let diagnostics = parser.diagnostics();
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].code, Some("L0005"));
+        assert_eq!(diagnostics[0].span, Some(Span::new(0, 1)));
+
+        let (parser, program) = parser_for_input("x @ 1;");
+        assert_eq!(program.to_string(), "x;\n1\n");
+        let diagnostics = parser.diagnostics();
+        assert_eq!(diagnostics[0].code, Some("L0005"));
+        assert_eq!(diagnostics[0].span, Some(Span::new(2, 3)));
+        assert!(parser.errors()[1..]
+            .iter()
+            .all(|err| !matches!(err, ParserError::UnhandledPrefix(_))));
+        // `x` only looks like a statement of its own because of the error.
+        assert!(parser.warnings().is_empty());
+
+        let (parser, _) = parser_for_input("“hi”");
+        assert_eq!(
+            parser.diagnostics()[0].suggestion.as_deref(),
+            Some("use straight quotes `\"` for strings")
+        );
+    }
+
+    #[test]
+    fn test_illegal_character_diagnostics() {
+        let (parser, program) = parser_for_input("let x = 5 – 3;\nx @");
+        assert_eq!(program.to_string(), "let x = 5;\n3;\nx\n");
+        let diagnostics = parser.diagnostics();
+        // The recovered `3;` also draws a warning.
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[2].code, Some("W0001"));
+        assert_eq!(diagnostics[0].code, Some("L0005"));
+        assert_eq!(diagnostics[0].message, "Illegal character '–'");
+        assert_eq!(diagnostics[0].span, Some(Span::new(10, 13)));
+        assert_eq!(
+            diagnostics[0].suggestion.as_deref(),
+            Some("did you mean `-`?")
+        );
+        assert_eq!(diagnostics[1].span, Some(Span::new(19, 20)));
+        assert_eq!(diagnostics[1].suggestion, None);
+
+        let (parser, _program) = parser_for_input("if (a = b) { c }");
+        let diagnostics = parser.diagnostics();
+        assert_eq!(diagnostics[0].code, Some("P0001"));
+        assert_eq!(
+            diagnostics[0].suggestion.as_deref(),
+            Some("did you mean `==`?")
+        );
+    }
+
+    #[test]
+    fn test_parse_program_result() {
+        let mut parser = Parser::from_input("let x = 5;");
+        assert_eq!(parser.parse_program().unwrap().len(), 1);
+
+        let mut parser = Parser::from_input("let x = 5; let = 6; x");
+        let failure = parser.parse_program().unwrap_err();
+        assert_eq!(failure.program.statements[0].to_string(), "let x = 5;");
+        assert_eq!(failure.errors.len(), parser.errors().len());
+        assert_eq!(
+            failure.to_string(),
+            "Expected next token to be Ident, got Assign instead\n\
+             No prefix parse function for Assign"
+        );
+
+        #[allow(deprecated)]
+        let program = Parser::from_input("let = 6;").parse_program_option();
+        assert!(program.is_some());
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let source = "let add = fn(a, b) {\n  a + b\n};\nadd(1, 2)";
+        let mut parser = Parser::from_reader(source.as_bytes());
+        let program = parser.parse_program().unwrap();
+        assert_no_parser_errors(&parser);
+        assert_eq!(program.to_string(), parser_for_input(source).1.to_string());
+    }
+
+    #[test]
+    fn test_hand_built_tokens() {
+        let tokens = vec![
+            Token::from(TokenKind::Let).at(Span::new(0, 3)),
+            Token::make_ident("x").at(Span::new(4, 5)),
+            Token::from(TokenKind::Assign).at(Span::new(6, 7)),
+            Token::make_int("1").at(Span::new(8, 9)),
+            Token::from(TokenKind::Plus).at(Span::new(10, 11)),
+        ];
+        let mut parser = Parser::new(Tokens::new(tokens));
+        let program = parser.parse_program().unwrap_err().program;
+        assert!(program.statements.is_empty());
+        assert_eq!(parser.errors()[0].code(), "P0003");
+        assert_eq!(parser.error_spans(), &[Span::new(11, 11)]);
+
+        // Tokens lexed once can be parsed again without the source.
+        let tokens = crate::lexer::lex_with_spans("f(1, [2])");
+        let mut parser = Parser::new(Tokens::new(tokens));
+        let program = parser.parse_program().unwrap();
+        assert_no_parser_errors(&parser);
+        assert_eq!(program.to_sexpr(), "(call f 1 (array 2))\n");
+
+        // Pipes only look at the tokens' kinds, so work without spans too.
+        let pipe = |grouped: bool| {
+            let mut tokens = vec![Token::make_ident("x"), Token::from(TokenKind::Pipe)];
+            if grouped {
+                tokens.push(Token::from(TokenKind::LParen));
+            }
+            tokens.extend([
+                Token::make_ident("g"),
+                Token::from(TokenKind::LParen),
+                Token::make_int("1"),
+                Token::from(TokenKind::RParen),
+            ]);
+            if grouped {
+                tokens.push(Token::from(TokenKind::RParen));
+            }
+            let mut parser = Parser::new(Tokens::new(tokens));
+            parser.parse_program().unwrap().to_string()
+        };
+        assert_eq!(pipe(false), "g(x, 1)\n");
+        assert_eq!(pipe(true), "g(1)(x)\n");
+    }
+
+    #[test]
+    fn test_return_statements() {
+        let input = r#"
+        return 5;
+        return 10;
+        return 993322;
+        "#;
+
+        let (parser, program) = parser_for_input(input);
         assert_no_parser_errors(&parser);
         assert_program_statements_len(&program, 3);
-        let values = vec!["5", "10", "993322"];
-        for (_expected_identifier, stmt) in values.iter().zip(program.statements.iter()) {
+        let values = [5, 10, 993322];
+        for (expected_value, stmt) in values.iter().zip(program.statements.iter()) {
             match stmt {
-                Statement::Return { token, .. } => {
-                    assert_eq!(token, &Token::Return);
+                Statement::Return { token, expr } => {
+                    assert_eq!(token, &Token::from(TokenKind::Return));
+                    assert_eq!(expr, &Expression::IntegerLiteral(*expected_value));
                 }
-                _ => assert!(false, "Expected ReturnStatement, got {:?}", stmt),
+                _ => panic!("Expected ReturnStatement, got {:?}", stmt),
             }
         }
     }
 
+    #[test]
+    fn test_return_without_semicolon() {
+        let (parser, program) = parser_for_input("return 5");
+        assert_no_parser_errors(&parser);
+        assert_program_statements_len(&program, 1);
+        assert_eq!(program.to_string(), "return 5;\n");
+    }
+
+    #[test]
+    fn test_return_without_value() {
+        let (parser, program) = parser_for_input("return; if (x) { return }");
+        assert_no_parser_errors(&parser);
+        assert_eq!(program.to_string(), "return;\nif (x) { return; }\n");
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let max = ParserOptions::default().max_nesting_depth;
+        let depth = max - 1;
+        let input = format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        let (parser, _program) = parser_for_input(&input);
+        assert_no_parser_errors(&parser);
+
+        // The limit can be raised for inputs known to be safe.
+        let input = format!("{}1{}", "(".repeat(max), ")".repeat(max));
+        let options = ParserOptions {
+            max_nesting_depth: max + 1,
+            ..ParserOptions::default()
+        };
+        let mut parser = Parser::from_input(&input).with_options(options);
+        assert!(parser.parse_program().is_ok(), "{:?}", parser.errors());
+
+        for input in [
+            format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000)),
+            "-".repeat(10_000) + "1",
+            "if (x) { ".repeat(10_000),
+        ] {
+            let (parser, _program) = parser_for_input(&input);
+            assert!(
+                matches!(
+                    parser.errors().first(),
+                    Some(ParserError::NestingTooDeep(depth)) if *depth == max
+                ),
+                "Expected ParserError::NestingTooDeep, saw {:?}",
+                parser.errors()
+            );
+        }
+    }
+
     #[test]
     fn test_identifier_expression() {
         let input = "foobar;";
@@ -506,6 +1635,28 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_expression_statement_token() {
+        let (parser, program) = parser_for_input("a + b; 5 * x;");
+        assert_no_parser_errors(&parser);
+        assert_program_statements_len(&program, 2);
+        assert_eq!(
+            program.statements[0],
+            Statement::Expression {
+                token: Token::make_ident("a"),
+                expr: Expression::new_infix(
+                    Identifier::new("a"),
+                    Token::from(TokenKind::Plus),
+                    Identifier::new("b")
+                ),
+            }
+        );
+        match &program.statements[1] {
+            Statement::Expression { token, .. } => assert_eq!(token, &Token::make_int("5")),
+            stmt => panic!("Expected Statement::Expression, got {:?}", stmt),
+        }
+    }
+
     #[test]
     fn test_integer_literal_expression() {
         let input = "5;";
@@ -523,31 +1674,33 @@ mod test {
             (
                 "true;",
                 Statement::Expression {
-                    token: Token::True,
+                    token: Token::from(TokenKind::True),
                     expr: Expression::Boolean(true),
                 },
             ),
             (
                 "false;",
                 Statement::Expression {
-                    token: Token::False,
+                    token: Token::from(TokenKind::False),
                     expr: Expression::Boolean(false),
                 },
             ),
             (
                 "let foobar = true;",
                 Statement::Let {
-                    token: Token::Let,
+                    token: Token::from(TokenKind::Let),
                     name: Identifier::new("foobar"),
                     value: Expression::Boolean(true),
+                    doc: None,
                 },
             ),
             (
                 "let foobar = false;",
                 Statement::Let {
-                    token: Token::Let,
+                    token: Token::from(TokenKind::Let),
                     name: Identifier::new("foobar"),
                     value: Expression::Boolean(false),
+                    doc: None,
                 },
             ),
         ];
@@ -562,10 +1715,31 @@ mod test {
     #[test]
     fn test_parsing_prefix_expressions() {
         let prefix_tests = vec![
-            ("!5;", Token::Bang, Expression::IntegerLiteral(5)),
-            ("-15;", Token::Minus, Expression::IntegerLiteral(15)),
-            ("!true;", Token::Bang, Expression::Boolean(true)),
-            ("!false;", Token::Bang, Expression::Boolean(false)),
+            (
+                "!5;",
+                Token::from(TokenKind::Bang),
+                Expression::IntegerLiteral(5),
+            ),
+            (
+                "-15;",
+                Token::from(TokenKind::Minus),
+                Expression::IntegerLiteral(15),
+            ),
+            (
+                "!true;",
+                Token::from(TokenKind::Bang),
+                Expression::Boolean(true),
+            ),
+            (
+                "!false;",
+                Token::from(TokenKind::Bang),
+                Expression::Boolean(false),
+            ),
+            (
+                "~5;",
+                Token::from(TokenKind::Tilde),
+                Expression::IntegerLiteral(5),
+            ),
         ];
 
         for (input, operator, right) in prefix_tests {
@@ -579,6 +1753,63 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_precedence_lower() {
+        let tiers = [
+            Precedence::Lowest,
+            Precedence::Pipe,
+            Precedence::Range,
+            Precedence::Equals,
+            Precedence::LessGreater,
+            Precedence::BitOr,
+            Precedence::BitXor,
+            Precedence::BitAnd,
+            Precedence::Shift,
+            Precedence::Sum,
+            Precedence::Product,
+            Precedence::Power,
+            Precedence::Prefix,
+            Precedence::Call,
+            Precedence::Index,
+        ];
+        assert_eq!(Precedence::Lowest.lower(), Precedence::Lowest);
+        for pair in tiers.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert_eq!(pair[1].lower(), pair[0]);
+        }
+    }
+
+    #[test]
+    fn test_right_associative_operators() {
+        let operators = [
+            TokenKind::Eq,
+            TokenKind::Lt,
+            TokenKind::Bar,
+            TokenKind::Caret,
+            TokenKind::Ampersand,
+            TokenKind::ShiftLeft,
+            TokenKind::Plus,
+            TokenKind::Asterisk,
+            TokenKind::Power,
+        ];
+        for &kind in operators.iter() {
+            if !Precedence::is_right_associative(kind) {
+                continue;
+            }
+            let op = kind.literal().unwrap();
+            let (_, program) = parser_for_input(&format!("a {0} b {0} c", op));
+            assert_eq!(program.to_string(), format!("(a {0} (b {0} c))\n", op));
+            // The right operand takes nothing which binds more loosely.
+            for &other in operators.iter() {
+                if Precedence::for_kind(other) < Precedence::for_kind(kind) {
+                    let other = other.literal().unwrap();
+                    let (_, program) = parser_for_input(&format!("a {} b {} c", op, other));
+                    assert_eq!(program.to_string(), format!("((a {} b) {} c)\n", op, other));
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_operator_precedence_parsing() {
         let precedence_tests = vec![
@@ -594,7 +1825,42 @@ mod test {
             ("a * b / c", "((a * b) / c)"),
             ("a + b / c", "(a + (b / c))"),
             ("a + b * c + d / e - f", "(((a + (b * c)) + (d / e)) - f)"),
-            ("3 + 4; -5 * 5", "(3 + 4)\n((-5) * 5)"),
+            ("2 ** 3 ** 2", "(2 ** (3 ** 2))"),
+            ("a | b ^ c & d", "(a | (b ^ (c & d)))"),
+            ("a & b << c + d", "(a & (b << (c + d)))"),
+            ("a << b >> c", "((a << b) >> c)"),
+            ("a & b == c | d", "((a & b) == (c | d))"),
+            ("a < b | c", "(a < (b | c))"),
+            ("~a & b", "((~a) & b)"),
+            ("x |> f | g", "(f | g)(x)"),
+            ("a * b ** c * d", "((a * (b ** c)) * d)"),
+            ("-a ** 2", "((-a) ** 2)"),
+            ("a ** -b", "(a ** (-b))"),
+            ("a ** b[0]", "(a ** (b[0]))"),
+            ("3 + 4; -5 * 5", "(3 + 4);\n((-5) * 5)"),
+            ("a + add(b * c) + d", "((a + add((b * c))) + d)"),
+            (
+                "add(a, b, 1, 2 * 3, 4 + 5, add(6, 7 * 8))",
+                "add(a, b, 1, (2 * 3), (4 + 5), add(6, (7 * 8)))",
+            ),
+            (
+                "add(a + b + c * d / f + g)",
+                "add((((a + b) + ((c * d) / f)) + g))",
+            ),
+            ("-f(x)[0]", "(-(f(x)[0]))"),
+            ("a.b(1)", "(a[\"b\"])(1)"),
+            ("x |> f", "f(x)"),
+            ("x |> f |> g(1)", "g(f(x), 1)"),
+            ("a + 1 |> f(b)", "f((a + 1), b)"),
+            ("x |> f(a) == y", "(f(a) == y)(x)"),
+            ("x |> (f(1))", "f(1)(x)"),
+            ("x |> (f)(1)", "f(x, 1)"),
+            ("1..10", "(1..10)"),
+            ("0..=n - 1", "(0..=(n - 1))"),
+            ("a..b == c..d", "((a..(b == c))..d)"),
+            ("1..n |> to_array", "to_array((1..n))"),
+            ("a.b..c.d", "((a[\"b\"])..(c[\"d\"]))"),
+            ("x |> fn(a) { a }", "fn(a) { a }(x)"),
             ("5 > 4 == 3 < 4", "((5 > 4) == (3 < 4))"),
             ("5 < 4 != 3 > 4", "((5 < 4) != (3 > 4))"),
             (
@@ -607,6 +1873,10 @@ mod test {
             ("(5 + 5) * 2 * (5 + 5)", "(((5 + 5) * 2) * (5 + 5))"),
             ("-(5 + 5)", "(-(5 + 5))"),
             ("!(true == true)", "(!(true == true))"),
+            ("a * b[2]", "(a * (b[2]))"),
+            ("-a[1][2]", "(-((a[1])[2]))"),
+            ("a[b + 1:c]", "(a[(b + 1):c])"),
+            (r#""abc" < "abd""#, r#"("abc" < "abd")"#),
         ];
 
         for (input, expected_output) in precedence_tests {
@@ -620,25 +1890,73 @@ mod test {
     #[test]
     fn test_parsing_infix_expressions() {
         let prefix_tests = vec![
-            ("5 + 5;", Expression::new_infix(5, Token::Plus, 5)),
-            ("5 - 5;", Expression::new_infix(5, Token::Minus, 5)),
-            ("5 * 5;", Expression::new_infix(5, Token::Asterisk, 5)),
-            ("5 / 5;", Expression::new_infix(5, Token::Slash, 5)),
-            ("5 > 5;", Expression::new_infix(5, Token::Gt, 5)),
-            ("5 < 5;", Expression::new_infix(5, Token::Lt, 5)),
-            ("5 == 5;", Expression::new_infix(5, Token::Eq, 5)),
-            ("5 != 5;", Expression::new_infix(5, Token::NotEq, 5)),
+            (
+                "5 + 5;",
+                Expression::new_infix(5, Token::from(TokenKind::Plus), 5),
+            ),
+            (
+                "5 - 5;",
+                Expression::new_infix(5, Token::from(TokenKind::Minus), 5),
+            ),
+            (
+                "5 * 5;",
+                Expression::new_infix(5, Token::from(TokenKind::Asterisk), 5),
+            ),
+            (
+                "5 / 5;",
+                Expression::new_infix(5, Token::from(TokenKind::Slash), 5),
+            ),
+            (
+                "5 ** 5;",
+                Expression::new_infix(5, Token::from(TokenKind::Power), 5),
+            ),
+            (
+                "5 << 5;",
+                Expression::new_infix(5, Token::from(TokenKind::ShiftLeft), 5),
+            ),
+            (
+                "5 >> 5;",
+                Expression::new_infix(5, Token::from(TokenKind::ShiftRight), 5),
+            ),
+            (
+                "5 & 5;",
+                Expression::new_infix(5, Token::from(TokenKind::Ampersand), 5),
+            ),
+            (
+                "5 | 5;",
+                Expression::new_infix(5, Token::from(TokenKind::Bar), 5),
+            ),
+            (
+                "5 ^ 5;",
+                Expression::new_infix(5, Token::from(TokenKind::Caret), 5),
+            ),
+            (
+                "5 > 5;",
+                Expression::new_infix(5, Token::from(TokenKind::Gt), 5),
+            ),
+            (
+                "5 < 5;",
+                Expression::new_infix(5, Token::from(TokenKind::Lt), 5),
+            ),
+            (
+                "5 == 5;",
+                Expression::new_infix(5, Token::from(TokenKind::Eq), 5),
+            ),
+            (
+                "5 != 5;",
+                Expression::new_infix(5, Token::from(TokenKind::NotEq), 5),
+            ),
             (
                 "true == true;",
-                Expression::new_infix(true, Token::Eq, true),
+                Expression::new_infix(true, Token::from(TokenKind::Eq), true),
             ),
             (
                 "false == false;",
-                Expression::new_infix(false, Token::Eq, false),
+                Expression::new_infix(false, Token::from(TokenKind::Eq), false),
             ),
             (
                 "true != false;",
-                Expression::new_infix(true, Token::NotEq, false),
+                Expression::new_infix(true, Token::from(TokenKind::NotEq), false),
             ),
         ];
         for (input, expected_expr) in prefix_tests {
@@ -668,7 +1986,11 @@ mod test {
             } => {
                 assert_eq!(
                     **condition,
-                    Expression::new_infix(Identifier::new("x"), Token::Lt, Identifier::new("y"))
+                    Expression::new_infix(
+                        Identifier::new("x"),
+                        Token::from(TokenKind::Lt),
+                        Identifier::new("y")
+                    )
                 );
                 assert_eq!(consequence.statements.len(), 1);
                 assert_statement_expression_eq(
@@ -677,14 +1999,10 @@ mod test {
                 );
                 assert!(alternative.is_none());
             }
-            _ => {
-                assert!(
-                    false,
-                    "Expected Statement::Expression, got {:?}",
-                    program.statements[0]
-                );
-                unreachable!();
-            }
+            _ => panic!(
+                "Expected Statement::Expression, got {:?}",
+                program.statements[0]
+            ),
         }
     }
 
@@ -707,7 +2025,11 @@ mod test {
             } => {
                 assert_eq!(
                     **condition,
-                    Expression::new_infix(Identifier::new("x"), Token::Lt, Identifier::new("y"))
+                    Expression::new_infix(
+                        Identifier::new("x"),
+                        Token::from(TokenKind::Lt),
+                        Identifier::new("y")
+                    )
                 );
                 assert_eq!(consequence.statements.len(), 1);
                 assert_statement_expression_eq(
@@ -721,17 +2043,477 @@ mod test {
                         &Expression::new_ident("y"),
                     );
                 } else {
-                    assert!(false, "Expected Some(alternative), got None");
+                    panic!("Expected Some(alternative), got None");
                 }
             }
-            _ => {
-                assert!(
-                    false,
-                    "Expected Statement::Expression, got {:?}",
-                    program.statements[0]
+            _ => panic!(
+                "Expected Statement::Expression, got {:?}",
+                program.statements[0]
+            ),
+        }
+    }
+
+    #[test]
+    fn test_else_if_expression() {
+        let (parser, program) = parser_for_input("if (a) { b } else if (c) { d } else { e }");
+        assert_no_parser_errors(&parser);
+        assert_program_statements_len(&program, 1);
+        assert_eq!(
+            program.to_string(),
+            "if (a) { b } else if (c) { d } else { e }\n"
+        );
+    }
+
+    #[test]
+    fn test_string_and_index_expressions() {
+        let (parser, program) = parser_for_input(r#""hello"[1]; "hello"[1:3]"#);
+        assert_no_parser_errors(&parser);
+        assert_program_statements_len(&program, 2);
+        let hello = || Box::new(Expression::StringLiteral("hello".to_owned()));
+        assert_statement_expression_eq(
+            &program.statements[0],
+            &Expression::Index {
+                left: hello(),
+                index: Box::new(Expression::IntegerLiteral(1)),
+            },
+        );
+        assert_statement_expression_eq(
+            &program.statements[1],
+            &Expression::Slice {
+                left: hello(),
+                start: Some(Box::new(Expression::IntegerLiteral(1))),
+                end: Some(Box::new(Expression::IntegerLiteral(3))),
+            },
+        );
+
+        let (parser, _) = parser_for_input("a[1");
+        assert_parser_errors_len(&parser, 1);
+        let (parser, _) = parser_for_input(r#"let s = "abc"#);
+        assert_eq!(parser.errors().last().unwrap().code(), "L0004");
+    }
+
+    #[test]
+    fn test_arrays_slices_and_members() {
+        for (input, expected) in [
+            ("[]", "[]"),
+            ("[1, 2 * 3, [a]]", "[1, (2 * 3), [a]]"),
+            ("arr[-1]", "(arr[(-1)])"),
+            ("arr[1:3]", "(arr[1:3])"),
+            ("arr[:2]", "(arr[:2])"),
+            ("arr[2:]", "(arr[2:])"),
+            ("arr[:]", "(arr[:])"),
+            ("[1, 2][0]", "([1, 2][0])"),
+            ("x; [1]", "x;\n[1]"),
+            ("config.timeout", "(config[\"timeout\"])"),
+            ("-a.b[0].c", "(-(((a[\"b\"])[0])[\"c\"]))"),
+            ("a.b * c.d", "((a[\"b\"]) * (c[\"d\"]))"),
+        ] {
+            let (parser, program) = parser_for_input(input);
+            assert_no_parser_errors(&parser);
+            assert_eq!(program.to_string().trim_end(), expected);
+        }
+        for input in ["[1, 2", "[1 2]", "arr[1:2:3]", "arr[]", "a.", "a.1", "a.if"] {
+            let (parser, _) = parser_for_input(input);
+            assert!(!parser.errors().is_empty(), "no errors for {}", input);
+        }
+    }
+
+    #[test]
+    fn test_destructuring_let() {
+        let (parser, program) =
+            parser_for_input("let [a, [b, c]] = arr; let {x, y} = point; let [] = e;");
+        assert_no_parser_errors(&parser);
+        assert_program_statements_len(&program, 3);
+        match &program.statements[0] {
+            Statement::Destructure { pattern, value, .. } => {
+                assert_eq!(
+                    pattern,
+                    &Pattern::Array(vec![
+                        Pattern::Identifier(Identifier::new("a")),
+                        Pattern::Array(vec![
+                            Pattern::Identifier(Identifier::new("b")),
+                            Pattern::Identifier(Identifier::new("c")),
+                        ]),
+                    ])
                 );
-                unreachable!();
+                assert_eq!(value, &Expression::new_ident("arr"));
             }
+            stmt => panic!("Expected destructuring let, got {:?}", stmt),
+        }
+        assert_eq!(
+            program.to_string(),
+            "let [a, [b, c]] = arr;\nlet {x, y} = point;\nlet [] = e;\n"
+        );
+
+        for input in [
+            "let [a, 1] = x;",
+            "let {x, [y]} = p;",
+            "let [a b] = x;",
+            "let [a] x;",
+        ] {
+            let (parser, _) = parser_for_input(input);
+            assert!(!parser.errors().is_empty(), "no errors for {}", input);
         }
     }
+
+    #[test]
+    fn test_const_statements() {
+        let (parser, program) = parser_for_input("const x = 5; const [a, b] = pair;");
+        assert_no_parser_errors(&parser);
+        match &program.statements[0] {
+            Statement::Let { token, name, .. } => {
+                assert_eq!(token, &Token::from(TokenKind::Const));
+                assert_eq!(name, &Identifier::new("x"));
+            }
+            stmt => panic!("Expected const, got {:?}", stmt),
+        }
+        assert_eq!(program.to_string(), "const x = 5;\nconst [a, b] = pair;\n");
+        let (parser, _) = parser_for_input("const = 5;");
+        assert!(matches!(
+            parser.errors()[0],
+            ParserError::ExpectedIdent(Token {
+                kind: TokenKind::Assign,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_tuple_sugar() {
+        for (input, expected) in [
+            ("let x, y = pair();", "let [x, y] = pair();"),
+            ("let [a, b], c = f;", "let [[a, b], c] = f;"),
+            ("return a, b + 1;", "return [a, (b + 1)];"),
+            ("fn() { return a, b }", "fn() { return [a, b]; }"),
+        ] {
+            let (parser, program) = parser_for_input(input);
+            assert_no_parser_errors(&parser);
+            assert_eq!(program.to_string().trim_end(), expected);
+        }
+        for input in ["let x, = f;", "let x, 1 = f;", "return a, ;"] {
+            let (parser, _) = parser_for_input(input);
+            assert!(!parser.errors().is_empty(), "no errors for {}", input);
+        }
+    }
+
+    #[test]
+    fn test_raw_strings() {
+        let (parser, program) = parser_for_input(r#"r"C:\path\n"; r"${name}""#);
+        assert_no_parser_errors(&parser);
+        assert_statement_expression_eq(
+            &program.statements[0],
+            &Expression::StringLiteral(r"C:\path\n".to_owned()),
+        );
+        assert_statement_expression_eq(
+            &program.statements[1],
+            &Expression::StringLiteral("${name}".to_owned()),
+        );
+        assert_eq!(program.to_string(), "\"C:\\path\\n\";\nr\"${name}\"\n");
+    }
+
+    #[test]
+    fn test_interpolated_strings() {
+        let (parser, program) = parser_for_input(r#""Hello, ${name}! You are ${age + 1}""#);
+        assert_no_parser_errors(&parser);
+        assert_statement_expression_eq(
+            &program.statements[0],
+            &Expression::Interpolated(vec![
+                StringPart::Text("Hello, ".to_owned()),
+                StringPart::Expr(Expression::new_ident("name")),
+                StringPart::Text("! You are ".to_owned()),
+                StringPart::Expr(Expression::new_infix(
+                    Expression::new_ident("age"),
+                    Token::from(TokenKind::Plus),
+                    Expression::IntegerLiteral(1),
+                )),
+            ]),
+        );
+        for (input, expected) in [
+            (r#""${a}${b}""#, r#""${a}${b}""#),
+            (r#""${"${x}"}""#, r#""${"${x}"}""#),
+            (r#""${f(fn() { 1 })}""#, r#""${f(fn() { 1 })}""#),
+            (r#""${a}"[0]"#, r#"("${a}"[0])"#),
+        ] {
+            let (parser, program) = parser_for_input(input);
+            assert_no_parser_errors(&parser);
+            assert_eq!(program.to_string().trim_end(), expected);
+        }
+        for input in [r#""${}""#, r#""${a b}""#, r#""${a"#] {
+            let (parser, _) = parser_for_input(input);
+            assert!(!parser.errors().is_empty(), "no errors for {}", input);
+        }
+        let (parser, _) = parser_for_input(r#""${a b}""#);
+        assert_eq!(
+            parser.errors()[0].suggestion().as_deref(),
+            Some("close the interpolation with `}`")
+        );
+    }
+
+    #[test]
+    fn test_function_literal() {
+        let (parser, program) = parser_for_input("fn(x, y) { x + y; }");
+        assert_no_parser_errors(&parser);
+        assert_program_statements_len(&program, 1);
+        match &program.statements[0] {
+            Statement::Expression {
+                expr:
+                    Expression::Function {
+                        parameters, body, ..
+                    },
+                ..
+            } => {
+                assert_eq!(parameters, &[Identifier::new("x"), Identifier::new("y")]);
+                assert_eq!(body.statements.len(), 1);
+                assert_statement_expression_eq(
+                    &body.statements[0],
+                    &Expression::new_infix(
+                        Identifier::new("x"),
+                        Token::from(TokenKind::Plus),
+                        Identifier::new("y"),
+                    ),
+                );
+            }
+            stmt => panic!("Expected function literal, got {:?}", stmt),
+        }
+
+        for (input, expected) in [("fn() {};", 0), ("fn(x) {};", 1), ("fn(x, y, z) {};", 3)] {
+            let (parser, program) = parser_for_input(input);
+            assert_no_parser_errors(&parser);
+            match &program.statements[0] {
+                Statement::Expression {
+                    expr: Expression::Function { parameters, .. },
+                    ..
+                } => assert_eq!(parameters.len(), expected),
+                stmt => panic!("Expected function literal, got {:?}", stmt),
+            }
+        }
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let input = "/// Adds.\n/// Twice.\nlet add = fn(a, b) { a + b };\n\
+                     let f = /// Inner.\nfn() { };\n\
+                     /// Dropped.\n1;\nlet x = 2;";
+        let (parser, program) = parser_for_input(input);
+        assert_no_parser_errors(&parser);
+        let docs: Vec<(Option<&str>, Option<&str>)> = program
+            .statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Statement::Let { value, doc, .. } => Some((
+                    doc.as_deref(),
+                    match value {
+                        Expression::Function { doc, .. } => doc.as_deref(),
+                        _ => None,
+                    },
+                )),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            docs,
+            vec![
+                (Some("Adds.\nTwice."), None),
+                (None, Some("Inner.")),
+                (None, None)
+            ]
+        );
+        assert!(program
+            .to_string()
+            .starts_with("/// Adds.\n/// Twice.\nlet add = fn(a, b) { (a + b) };\n"));
+    }
+
+    #[test]
+    fn test_dialect_keywords() {
+        let keywords = Keywords::new()
+            .alias("func", Token::from(TokenKind::Function))
+            .alias_sequence(
+                "elsif",
+                vec![Token::from(TokenKind::Else), Token::from(TokenKind::If)],
+            );
+        let input = "if (a) { b } elsif (c) { d }";
+        let mut parser = Parser::new(Lexer::new(input).with_keywords(keywords));
+        let program = parser.parse_program().unwrap();
+        assert_no_parser_errors(&parser);
+
+        let (_, expected) = parser_for_input("if (a) { b } else if (c) { d }");
+        assert_eq!(program.statements, expected.statements);
+    }
+
+    #[test]
+    fn test_case_insensitive_keywords_normalise() {
+        let features = LanguageFeatures {
+            case_insensitive_keywords: true,
+        };
+        let input = "LET x = If (True) { FALSE } ELSE { x };";
+        let mut parser = Parser::new(Lexer::new(input).with_features(features));
+        let program = parser.parse_program().unwrap();
+        assert_no_parser_errors(&parser);
+        assert_eq!(
+            program.to_string(),
+            "let x = if (true) { false } else { x };\n"
+        );
+    }
+
+    fn parse_with_options(input: &str, options: ParserOptions) -> Result<Program, ParseFailure> {
+        Parser::from_input(input)
+            .with_options(options)
+            .parse_program()
+    }
+
+    #[test]
+    fn test_require_semicolons() {
+        let options = ParserOptions {
+            require_semicolons: true,
+            ..ParserOptions::default()
+        };
+        let input = "let f = fn(x) { x + 1 };\nf(1);\nif (f) { 1 } else { 2 }\n";
+        assert!(parse_with_options(input, options).is_ok());
+
+        let failure = parse_with_options("f(1)\nf(2);", options).unwrap_err();
+        assert!(matches!(
+            failure.errors[..],
+            [ParserError::ExpectedToken {
+                expected: TokenKind::Semicolon,
+                ..
+            }]
+        ));
+        assert!(parse_with_options("f(1)", options).is_err());
+        assert!(parse_with_options("f(1)\nf(2);", ParserOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_trailing_commas() {
+        let options = ParserOptions {
+            trailing_commas: true,
+            ..ParserOptions::default()
+        };
+        let program = parse_with_options("f(1, 2,);\n[3,]", options).unwrap();
+        assert_eq!(program.to_string(), "f(1, 2);\n[3]\n");
+        assert!(parse_with_options("f(,)", options).is_err());
+        assert!(parse_with_options("[1, 2,]", ParserOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_book_options() {
+        for (input, at, extension) in [
+            ("1..3", "..", Extension::Ranges),
+            ("1..=3", "..=", Extension::Ranges),
+            ("x |> f", "|>", Extension::Pipes),
+            ("2 ** 3", "**", Extension::Power),
+            ("a << 1", "<<", Extension::Bitwise),
+            ("~a", "~", Extension::Bitwise),
+            ("try { 1 } catch (e) { 2 }", "try", Extension::TryCatch),
+            ("const x = 1;", "const", Extension::Constants),
+            ("let [a, b] = x;", "[", Extension::Destructuring),
+            ("let {a} = x;", "{", Extension::Destructuring),
+            ("let a, b = x;", ",", Extension::Destructuring),
+            ("\"a${b}\"", "\"", Extension::Interpolation),
+            ("a.b", ".", Extension::MemberAccess),
+            ("a[1:2]", ":", Extension::Slices),
+            ("a[:2]", ":", Extension::Slices),
+        ] {
+            let mut parser = Parser::from_input(input).with_options(ParserOptions::book());
+            let failure = parser.parse_program().unwrap_err();
+            assert!(
+                matches!(failure.errors[0], ParserError::Disabled(disabled) if disabled == extension),
+                "{}: {:?}",
+                input,
+                failure.errors
+            );
+            assert_eq!(parser.error_spans()[0].start, input.find(at).unwrap());
+            assert!(parse_with_options(input, ParserOptions::default()).is_ok());
+        }
+        assert!(parse_with_options("let x = [1, 2]; x[0] * 2", ParserOptions::book()).is_ok());
+        assert_eq!(
+            ParserError::Disabled(Extension::Pipes).to_string(),
+            "`|>` is not allowed in this dialect"
+        );
+    }
+
+    #[test]
+    fn test_try_expression() {
+        let input = "try { x } catch (err) { err }";
+        let (parser, program) = parser_for_input(input);
+        assert_no_parser_errors(&parser);
+        assert_program_statements_len(&program, 1);
+
+        match program.statements.first().unwrap() {
+            Statement::Expression {
+                expr:
+                    Expression::Try {
+                        body,
+                        binding,
+                        handler,
+                    },
+                ..
+            } => {
+                assert_eq!(body.statements.len(), 1);
+                assert_statement_expression_eq(
+                    body.statements.first().unwrap(),
+                    &Expression::new_ident("x"),
+                );
+                assert_eq!(binding, &Identifier::new("err"));
+                assert_eq!(handler.statements.len(), 1);
+                assert_statement_expression_eq(
+                    handler.statements.first().unwrap(),
+                    &Expression::new_ident("err"),
+                );
+            }
+            _ => panic!(
+                "Expected Statement::Expression, got {:?}",
+                program.statements[0]
+            ),
+        }
+    }
+
+    #[test]
+    fn test_invalid_try_expression() {
+        let (parser, _program) = parser_for_input("try { x } (err) { err }");
+        let errors = parser.errors();
+        assert!(
+            match errors.first() {
+                Some(ParserError::ExpectedToken { expected, saw }) => {
+                    assert_eq!(*expected, TokenKind::Catch);
+                    assert_eq!(saw, &Token::from(TokenKind::LParen));
+                    true
+                }
+                _ => false,
+            },
+            "Expected ParserError::ExpectedToken, saw {:?}",
+            errors
+        );
+
+        let (parser, _program) = parser_for_input("try { x } catch { err }");
+        assert!(
+            match parser.errors().first() {
+                Some(ParserError::ExpectedToken { expected, saw }) => {
+                    assert_eq!(*expected, TokenKind::LParen);
+                    assert_eq!(saw, &Token::from(TokenKind::LBrace));
+                    true
+                }
+                _ => false,
+            },
+            "Expected ParserError::ExpectedToken, saw {:?}",
+            parser.errors()
+        );
+    }
+
+    /// Snapshot the printed program, errors and AST for every file under
+    /// `testdata/`. Review changes with `cargo insta review`.
+    #[test]
+    fn test_testdata_snapshots() {
+        insta::glob!("../testdata", "*.monkey", |path| {
+            let input = std::fs::read_to_string(path).unwrap();
+            let (parser, program) = parser_for_input(&input);
+            let errors: Vec<String> = parser.errors().iter().map(|err| err.to_string()).collect();
+            let snapshot = format!(
+                "{}\n-- errors --\n{}\n\n-- ast --\n{:#?}",
+                program,
+                errors.join("\n"),
+                program.statements
+            );
+            insta::assert_snapshot!(snapshot);
+        });
+    }
 }
@@ -0,0 +1,468 @@
+//! A source formatter: unlike `Display`, which brackets every operator, the
+//! printer only emits the parentheses precedence requires, and lays blocks
+//! out over several lines when they don't fit on one.
+
+use crate::ast::{self, BlockStatement, Expression, Program, Statement, StringPart};
+use crate::parser::Precedence;
+
+/// Layout settings for the printer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrinterOptions {
+    /// Spaces per level of block nesting.
+    pub indent_width: usize,
+    /// The column statements should fit within. A statement that doesn't
+    /// fit has its blocks broken over several lines; with no limit, blocks
+    /// are only broken where a doc comment forces it. Nothing breaks
+    /// within an expression, so long expressions may still overrun.
+    pub max_line_len: Option<usize>,
+}
+
+impl Default for PrinterOptions {
+    fn default() -> Self {
+        PrinterOptions {
+            indent_width: 4,
+            max_line_len: Some(80),
+        }
+    }
+}
+
+/// Format `program`, one top-level statement per line.
+pub fn print_program(program: &Program, options: &PrinterOptions) -> String {
+    let mut printer = Printer::new(options, 0, false);
+    let last = program.statements.len().saturating_sub(1);
+    for (idx, stmt) in program.statements.iter().enumerate() {
+        printer.write_statement(stmt);
+        if idx < last && matches!(stmt, Statement::Expression { .. }) {
+            printer.out.push(';');
+        }
+        printer.out.push('\n');
+    }
+    printer.out
+}
+
+/// Format `expr` as though it started a line.
+pub fn print_expression(expr: &Expression, options: &PrinterOptions) -> String {
+    let mut printer = Printer::new(options, 0, false);
+    printer.write_expression(expr);
+    printer.out
+}
+
+/// The binding power of `expr` as an operand: atoms, keyword expressions
+/// and postfix calls and indexing bind tightest, as nothing can split them.
+fn precedence(expr: &Expression) -> Precedence {
+    match expr {
+        Expression::Infix { operator, .. } => Precedence::for_kind(operator.kind),
+        Expression::Prefix { .. } => Precedence::Prefix,
+        Expression::Range { .. } => Precedence::Range,
+        _ => Precedence::Index,
+    }
+}
+
+/// Writes nodes recursively; the parser limits nesting, so the depth of
+/// any parsed tree is bounded.
+struct Printer<'o> {
+    options: &'o PrinterOptions,
+    out: String,
+    indent: usize,
+    /// Write everything on one line.
+    flat: bool,
+    /// Set when flat output met something which needs a line break.
+    needs_break: bool,
+}
+
+impl<'o> Printer<'o> {
+    fn new(options: &'o PrinterOptions, indent: usize, flat: bool) -> Self {
+        Printer {
+            options,
+            out: String::new(),
+            indent,
+            flat,
+            needs_break: false,
+        }
+    }
+
+    /// Render a statement on one line, if it can be.
+    fn flat_statement(&self, stmt: &Statement) -> Option<String> {
+        let mut printer = Printer::new(self.options, self.indent, true);
+        printer.write_statement(stmt);
+        if printer.needs_break {
+            None
+        } else {
+            Some(printer.out)
+        }
+    }
+
+    fn fits(&self, text: &str) -> bool {
+        let column = match self.out.rfind('\n') {
+            Some(idx) => self.out[idx + 1..].chars().count(),
+            None => self.out.chars().count(),
+        };
+        self.options
+            .max_line_len
+            .map_or(true, |max| column + text.chars().count() <= max)
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        let width = self.indent * self.options.indent_width;
+        self.out.extend(std::iter::repeat(' ').take(width));
+    }
+
+    fn write_doc(&mut self, doc: &Option<String>) {
+        if let Some(doc) = doc {
+            self.needs_break |= self.flat;
+            for line in doc.split('\n') {
+                self.out.push_str("/// ");
+                self.out.push_str(line);
+                self.newline();
+            }
+        }
+    }
+
+    fn write_statement(&mut self, stmt: &Statement) {
+        if !self.flat {
+            if let Some(text) = self.flat_statement(stmt) {
+                if self.fits(&text) {
+                    self.out.push_str(&text);
+                    return;
+                }
+            }
+        }
+        match stmt {
+            Statement::Let {
+                token,
+                name,
+                value,
+                doc,
+            } => {
+                self.write_doc(doc);
+                self.out.push_str(&token.literal);
+                self.out.push(' ');
+                self.out.push_str(&name.0);
+                if *value != Expression::Nothing {
+                    self.out.push_str(" = ");
+                    self.write_expression(value);
+                }
+                self.out.push(';');
+            }
+            Statement::Destructure {
+                token,
+                pattern,
+                value,
+            } => {
+                self.out.push_str(&token.literal);
+                self.out.push(' ');
+                self.out.push_str(&pattern.to_string());
+                self.out.push_str(" = ");
+                self.write_expression(value);
+                self.out.push(';');
+            }
+            Statement::Return { expr, .. } => {
+                self.out.push_str("return");
+                if *expr != Expression::Nothing {
+                    self.out.push(' ');
+                    self.write_expression(expr);
+                }
+                self.out.push(';');
+            }
+            Statement::Expression { expr, .. } => self.write_expression(expr),
+        }
+    }
+
+    fn write_block(&mut self, block: &BlockStatement) {
+        if block.statements.is_empty() {
+            self.out.push_str("{ }");
+            return;
+        }
+        let last = block.statements.len() - 1;
+        self.out.push('{');
+        self.indent += 1;
+        for (idx, stmt) in block.statements.iter().enumerate() {
+            if self.flat {
+                self.out.push(' ');
+            } else {
+                self.newline();
+            }
+            self.write_statement(stmt);
+            if idx < last && matches!(stmt, Statement::Expression { .. }) {
+                self.out.push(';');
+            }
+        }
+        self.indent -= 1;
+        if self.flat {
+            self.out.push(' ');
+        } else {
+            self.newline();
+        }
+        self.out.push('}');
+    }
+
+    fn write_operand(&mut self, expr: &Expression, bracketed: bool) {
+        if bracketed {
+            self.out.push('(');
+        }
+        self.write_expression(expr);
+        if bracketed {
+            self.out.push(')');
+        }
+    }
+
+    fn write_list(&mut self, exprs: &[Expression]) {
+        for (idx, expr) in exprs.iter().enumerate() {
+            if idx > 0 {
+                self.out.push_str(", ");
+            }
+            self.write_expression(expr);
+        }
+    }
+
+    fn write_expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Identifier(name) => self.out.push_str(&name.0),
+            Expression::IntegerLiteral(value) => self.out.push_str(&value.to_string()),
+            Expression::StringLiteral(value) => self.out.push_str(&ast::quote_string(value)),
+            Expression::Interpolated(parts) => {
+                self.out.push('"');
+                for part in parts {
+                    match part {
+                        StringPart::Text(text) => self.out.push_str(text),
+                        StringPart::Expr(expr) => {
+                            self.out.push_str("${");
+                            self.write_expression(expr);
+                            self.out.push('}');
+                        }
+                    }
+                }
+                self.out.push('"');
+            }
+            Expression::Boolean(value) => self.out.push_str(&value.to_string()),
+            Expression::Nothing => {}
+            Expression::Prefix { operator, right } => {
+                self.out.push_str(&operator.literal);
+                self.write_operand(right, precedence(right) < Precedence::Prefix);
+            }
+            Expression::Infix {
+                left,
+                operator,
+                right,
+            } => {
+                // An equal-precedence operand only needs brackets on the side
+                // the operator doesn't group from.
+                let own = Precedence::for_kind(operator.kind);
+                let right_associative = Precedence::is_right_associative(operator.kind);
+                self.write_operand(
+                    left,
+                    precedence(left) < own || right_associative && precedence(left) == own,
+                );
+                self.out.push(' ');
+                self.out.push_str(&operator.literal);
+                self.out.push(' ');
+                self.write_operand(
+                    right,
+                    precedence(right) < own || !right_associative && precedence(right) == own,
+                );
+            }
+            Expression::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.out.push_str("if (");
+                self.write_expression(condition);
+                self.out.push_str(") ");
+                self.write_block(consequence);
+                if let Some(alternative) = alternative {
+                    self.out.push_str(" else ");
+                    match ast::else_if(alternative) {
+                        Some(Statement::Expression { expr, .. }) => self.write_expression(expr),
+                        _ => self.write_block(alternative),
+                    }
+                }
+            }
+            Expression::Try {
+                body,
+                binding,
+                handler,
+            } => {
+                self.out.push_str("try ");
+                self.write_block(body);
+                self.out.push_str(" catch (");
+                self.out.push_str(&binding.0);
+                self.out.push_str(") ");
+                self.write_block(handler);
+            }
+            Expression::Function {
+                parameters,
+                body,
+                doc,
+            } => {
+                self.write_doc(doc);
+                self.out.push_str("fn(");
+                let names: Vec<&str> = parameters.iter().map(|p| p.0.as_str()).collect();
+                self.out.push_str(&names.join(", "));
+                self.out.push_str(") ");
+                self.write_block(body);
+            }
+            Expression::Index { left, index } => {
+                self.write_operand(left, precedence(left) < Precedence::Call);
+                self.out.push('[');
+                self.write_expression(index);
+                self.out.push(']');
+            }
+            Expression::Slice { left, start, end } => {
+                self.write_operand(left, precedence(left) < Precedence::Call);
+                self.out.push('[');
+                if let Some(start) = start {
+                    self.write_expression(start);
+                }
+                self.out.push(':');
+                if let Some(end) = end {
+                    self.write_expression(end);
+                }
+                self.out.push(']');
+            }
+            Expression::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                self.write_operand(start, precedence(start) < Precedence::Range);
+                self.out.push_str(if *inclusive { "..=" } else { ".." });
+                self.write_operand(end, precedence(end) <= Precedence::Range);
+            }
+            Expression::ArrayLiteral(elements) => {
+                self.out.push('[');
+                self.write_list(elements);
+                self.out.push(']');
+            }
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                self.write_operand(function, precedence(function) < Precedence::Call);
+                self.out.push('(');
+                self.write_list(arguments);
+                self.out.push(')');
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::Parser;
+    use proptest::prelude::*;
+
+    fn parse(input: &str) -> Program {
+        let mut parser = Parser::from_input(input);
+        let program = parser.parse_program().unwrap();
+        assert!(
+            parser.errors().is_empty(),
+            "{:?} in {}",
+            parser.errors(),
+            input
+        );
+        program
+    }
+
+    fn format(input: &str, options: &PrinterOptions) -> String {
+        print_program(&parse(input), options)
+    }
+
+    #[test]
+    fn test_minimal_parens() {
+        let options = PrinterOptions::default();
+        for (input, expected) in [
+            ("(a + b) * c", "(a + b) * c\n"),
+            ("a + (b * c)", "a + b * c\n"),
+            ("(a - b) - c", "a - b - c\n"),
+            ("a - (b - c)", "a - (b - c)\n"),
+            ("a ** (b ** c)", "a ** b ** c\n"),
+            ("(a ** b) ** c", "(a ** b) ** c\n"),
+            ("(-a) ** (b * c)", "-a ** (b * c)\n"),
+            ("-(a + b)", "-(a + b)\n"),
+            ("(-a) * !(b == c)", "-a * !(b == c)\n"),
+            ("(1 < 2) == (3 > 4)", "1 < 2 == 3 > 4\n"),
+            ("-(-a)", "--a\n"),
+            ("(-a)[0]", "(-a)[0]\n"),
+            ("-(a[0])", "-a[0]\n"),
+            ("(a[0])[1:(b + 1)]", "a[0][1:b + 1]\n"),
+            ("[(a + b), [c]][:-1]", "[a + b, [c]][:-1]\n"),
+            ("(f(x))[0]", "f(x)[0]\n"),
+            ("(-f)(x, y + 1)", "(-f)(x, y + 1)\n"),
+            ("x |> f |> g(1)", "g(f(x), 1)\n"),
+            ("(0..(n + 1))", "0..n + 1\n"),
+            ("0..(1..2)", "0..(1..2)\n"),
+            ("(a..b) == c", "(a..b) == c\n"),
+        ] {
+            assert_eq!(format(input, &options), expected);
+        }
+    }
+
+    #[test]
+    fn test_line_breaking() {
+        let input = "let f = fn(x) { if (x > 10) { return x * 2; } else { x } };";
+        assert_eq!(
+            format(input, &PrinterOptions::default()),
+            "let f = fn(x) { if (x > 10) { return x * 2; } else { x } };\n"
+        );
+        assert_eq!(
+            format(
+                input,
+                &PrinterOptions {
+                    indent_width: 2,
+                    max_line_len: Some(30),
+                }
+            ),
+            "let f = fn(x) {\n  if (x > 10) {\n    return x * 2;\n  } else {\n    x\n  }\n};\n"
+        );
+    }
+
+    #[test]
+    fn test_unlimited_width() {
+        let options = PrinterOptions {
+            max_line_len: None,
+            ..PrinterOptions::default()
+        };
+        let input = format!("let x = {};", vec!["a"; 100].join(" + "));
+        assert_eq!(format(&input, &options).lines().count(), 1);
+    }
+
+    #[test]
+    fn test_doc_comments_break_lines() {
+        let input = "/// Outer.\nlet f = fn() { /// Inner.\nlet g = 1; g };";
+        assert_eq!(
+            format(input, &PrinterOptions::default()),
+            "/// Outer.\nlet f = fn() {\n    /// Inner.\n    let g = 1;\n    g\n};\n"
+        );
+    }
+
+    #[test]
+    fn test_statement_separators() {
+        assert_eq!(
+            format("a; -b; if (c) { d; -e }", &PrinterOptions::default()),
+            "a;\n-b;\nif (c) { d; -e }\n"
+        );
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(1000))]
+
+        #[test]
+        fn test_output_reparses(
+            program in any::<Program>(),
+            indent_width in 0..5usize,
+            max_line_len in proptest::option::of(0..60usize),
+        ) {
+            let options = PrinterOptions { indent_width, max_line_len };
+            let source = print_program(&program, &options);
+            let mut parser = Parser::from_input(&source);
+            let parsed = parser.parse_program().unwrap();
+            prop_assert!(parser.errors().is_empty(), "{:?} in {}", parser.errors(), source);
+            // Expression statements record their first token, which now
+            // depends on the brackets, so compare the fully bracketed forms.
+            prop_assert_eq!(parsed.to_string(), program.to_string(), "in {}", source);
+        }
+    }
+}
@@ -1,28 +1,471 @@
-use crate::lexer::Lexer;
-use crate::token::Token;
+use crate::ast::{Expression, Identifier, Statement};
+use crate::diagnostic::Diagnostic;
+use crate::json;
+use crate::lexer::{Lexer, LexerError};
+use crate::parser::Parser;
+use crate::token::{TokenKind, KEYWORDS};
 use std::io::{self, BufRead, BufReader, Read, Write};
 
 const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+/// The file name given in JSON diagnostics.
+const REPL_FILE: &str = "<repl>";
+
+const HELP: &str = "\
+Enter Monkey source to parse it and print the resulting program, or its
+tokens if the REPL was started with `--tokens`. Input with unclosed
+brackets or a trailing operator continues on the next line; enter a blank
+line to abandon it.
+
+Commands:
+  :ast <source>     pretty-print the parse tree for <source>
+  :tokens <source>  dump the token stream for <source>, with byte spans
+  :env              list the bindings made by `let` so far
+  :complete <text>  list keywords and bound names starting with <text>
+  :help             show this message";
+
+/// How the REPL renders its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Human-readable output behind a prompt.
+    Text,
+    /// One JSON object per line and no prompt, for driving the REPL from
+    /// another program.
+    Json,
+}
+
+/// What the REPL prints for source entered without a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    /// The parsed program, or its parser errors.
+    Program,
+    /// The token stream, as `:tokens` prints it.
+    Tokens,
+}
 
 pub fn start<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> io::Result<()> {
+    start_with_mode(reader, writer, OutputMode::Text)
+}
+
+pub fn start_with_mode<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    mode: OutputMode,
+) -> io::Result<()> {
+    start_with_view(reader, writer, mode, View::Program)
+}
+
+pub fn start_with_view<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    mode: OutputMode,
+    view: View,
+) -> io::Result<()> {
     let mut buffer = String::new();
+    let mut source = String::new();
     let mut reader = BufReader::new(reader);
+    let mut repl = Repl {
+        writer,
+        mode,
+        view,
+        bindings: vec![],
+    };
     loop {
-        write!(writer, "{}", PROMPT)?;
-        writer.flush()?;
+        if mode == OutputMode::Text {
+            let prompt = if source.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            };
+            write!(repl.writer, "{}", prompt)?;
+            repl.writer.flush()?;
+        }
         buffer.clear();
         let line_len = reader.read_line(&mut buffer)?;
         if line_len == 0 {
+            if !source.is_empty() {
+                repl.handle_line(&source)?;
+            }
+            return Ok(());
+        }
+        if !source.is_empty() && buffer.trim().is_empty() {
+            source.clear();
+            continue;
+        }
+        source.push_str(&buffer);
+        if !source.trim_start().starts_with(':') && is_incomplete(&source) {
+            continue;
+        }
+        repl.handle_line(&source)?;
+        repl.writer.flush()?;
+        source.clear();
+    }
+}
+
+/// Check whether `source` stops part-way through a construct: with an
+/// unclosed bracket or string, or after an operator expecting a right operand.
+fn is_incomplete(source: &str) -> bool {
+    let mut lexer = Lexer::new(source);
+    let mut depth = 0;
+    let mut last = TokenKind::EOF;
+    loop {
+        let kind = lexer.next_token().kind;
+        match kind {
+            TokenKind::LParen | TokenKind::LBrace | TokenKind::LBracket => depth += 1,
+            TokenKind::RParen | TokenKind::RBrace | TokenKind::RBracket => depth -= 1,
+            TokenKind::EOF => break,
+            _ => {}
+        }
+        last = kind;
+    }
+    depth > 0
+        || lexer.error() == Some(&LexerError::UnterminatedString)
+        || matches!(
+            last,
+            TokenKind::Assign
+                | TokenKind::Plus
+                | TokenKind::Minus
+                | TokenKind::Bang
+                | TokenKind::Asterisk
+                | TokenKind::Slash
+                | TokenKind::Power
+                | TokenKind::Eq
+                | TokenKind::NotEq
+                | TokenKind::Lt
+                | TokenKind::Gt
+                | TokenKind::ShiftLeft
+                | TokenKind::ShiftRight
+                | TokenKind::Ampersand
+                | TokenKind::Bar
+                | TokenKind::Caret
+                | TokenKind::Tilde
+                | TokenKind::Comma
+                | TokenKind::Colon
+                | TokenKind::Dot
+                | TokenKind::Pipe
+                | TokenKind::DotDot
+                | TokenKind::DotDotEq
+        )
+}
+
+struct Repl<'w, W: Write> {
+    writer: &'w mut W,
+    mode: OutputMode,
+    view: View,
+    /// `let` bindings seen so far, in definition order.
+    bindings: Vec<(Identifier, Expression)>,
+}
+
+impl<'w, W: Write> Repl<'w, W> {
+    fn handle_line(&mut self, line: &str) -> io::Result<()> {
+        let line = line.trim();
+        if !line.starts_with(':') {
+            return match self.view {
+                View::Program => self.parse(line),
+                View::Tokens => self.tokens(line),
+            };
+        }
+        let (command, rest) = match line.find(char::is_whitespace) {
+            Some(idx) => (&line[..idx], line[idx..].trim()),
+            None => (line, ""),
+        };
+        match command {
+            ":ast" => self.ast(rest),
+            ":tokens" => self.tokens(rest),
+            ":env" => self.env(),
+            ":complete" => self.write_completions(rest),
+            ":help" => self.write_output("help", HELP),
+            _ => self.write_error(&format!(
+                "Unknown command {}; try :help for a list of commands",
+                command
+            )),
+        }
+    }
+
+    fn parse(&mut self, source: &str) -> io::Result<()> {
+        let mut parser = Parser::from_input(source);
+        let program = parser
+            .parse_program()
+            .unwrap_or_else(|failure| failure.program);
+        if !parser.errors().is_empty() {
+            for diagnostic in parser.diagnostics() {
+                self.write_diagnostic(&diagnostic, source)?;
+            }
             return Ok(());
         }
-        let mut lexer = Lexer::new(&buffer);
-        let mut tok = lexer.next_token();
+        for stmt in &program.statements {
+            if let Statement::Let { name, value, .. } = stmt {
+                self.bindings.retain(|(bound, _)| bound != name);
+                self.bindings.push((name.clone(), value.clone()));
+            }
+        }
+        self.write_output("program", program.to_string().trim_end())
+    }
+
+    fn ast(&mut self, source: &str) -> io::Result<()> {
+        let mut parser = Parser::from_input(source);
+        let program = parser
+            .parse_program()
+            .unwrap_or_else(|failure| failure.program);
+        for diagnostic in parser.diagnostics() {
+            self.write_diagnostic(&diagnostic, source)?;
+        }
+        self.write_output("ast", &format!("{:#?}", program.statements))
+    }
+
+    fn tokens(&mut self, source: &str) -> io::Result<()> {
+        let mut lexer = Lexer::new(source);
         loop {
-            writeln!(writer, "{:?}", tok)?;
-            if tok == Token::EOF {
+            let tok = lexer.next_token();
+            match self.mode {
+                OutputMode::Text => writeln!(self.writer, "{} {:?}", tok.span, tok)?,
+                OutputMode::Json => writeln!(
+                    self.writer,
+                    "{}",
+                    json::object(&[
+                        ("type", json::quote("token")),
+                        ("token", json::quote(tok.name())),
+                        ("literal", json::quote(&tok.literal)),
+                        ("start", tok.span.start.to_string()),
+                        ("end", tok.span.end.to_string()),
+                    ])
+                )?,
+            }
+            if tok == TokenKind::EOF {
                 break;
             }
-            tok = lexer.next_token();
         }
+        if let Some(err) = lexer.error() {
+            self.write_diagnostic(&Diagnostic::from(err), source)?;
+        }
+        Ok(())
+    }
+
+    fn env(&mut self) -> io::Result<()> {
+        let listing: Vec<String> = self
+            .bindings
+            .iter()
+            .map(|(name, value)| format!("{} = {}", name, value))
+            .collect();
+        self.write_output("env", &listing.join("\n"))
+    }
+
+    /// Completion candidates for `prefix`: keywords and bound names, sorted
+    /// and without duplicates.
+    fn complete(&self, prefix: &str) -> Vec<String> {
+        let mut candidates: Vec<String> = KEYWORDS
+            .iter()
+            .filter_map(|keyword| keyword.literal())
+            .map(str::to_owned)
+            .chain(self.bindings.iter().map(|(name, _)| name.0.clone()))
+            .filter(|candidate| candidate.starts_with(prefix))
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+
+    fn write_completions(&mut self, prefix: &str) -> io::Result<()> {
+        let candidates = self.complete(prefix);
+        match self.mode {
+            OutputMode::Text => self.write_output("completions", &candidates.join("\n")),
+            OutputMode::Json => {
+                let values: Vec<String> = candidates.iter().map(|c| json::quote(c)).collect();
+                writeln!(
+                    self.writer,
+                    "{}",
+                    json::object(&[
+                        ("type", json::quote("completions")),
+                        ("output", json::array(&values)),
+                    ])
+                )
+            }
+        }
+    }
+
+    fn write_output(&mut self, kind: &str, output: &str) -> io::Result<()> {
+        match self.mode {
+            OutputMode::Text if output.is_empty() => Ok(()),
+            OutputMode::Text => writeln!(self.writer, "{}", output),
+            OutputMode::Json => writeln!(
+                self.writer,
+                "{}",
+                json::object(&[("type", json::quote(kind)), ("output", json::quote(output))])
+            ),
+        }
+    }
+
+    fn write_error(&mut self, message: &str) -> io::Result<()> {
+        self.write_diagnostic(&Diagnostic::error(message.to_owned()), "")
+    }
+
+    fn write_diagnostic(&mut self, diagnostic: &Diagnostic, source: &str) -> io::Result<()> {
+        match self.mode {
+            OutputMode::Text => {
+                writeln!(
+                    self.writer,
+                    "{}: {}",
+                    diagnostic.severity, diagnostic.message
+                )
+            }
+            OutputMode::Json => writeln!(self.writer, "{}", diagnostic.to_json(REPL_FILE, source)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run(input: &str, mode: OutputMode) -> String {
+        let mut output = vec![];
+        start_with_mode(&mut input.as_bytes(), &mut output, mode).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_text_mode() {
+        assert_eq!(
+            run("1 + 2 * 3\nlet = 5\n", OutputMode::Text),
+            ">> (1 + (2 * 3))\n\
+             >> error: Expected next token to be Ident, got Assign instead\n\
+             error: No prefix parse function for Assign\n\
+             >> "
+        );
+    }
+
+    #[test]
+    fn test_json_mode() {
+        let output = run("let x = 1;\n:tokens -x\n", OutputMode::Json);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                r#"{"type":"program","output":"let x = 1;"}"#,
+                r#"{"type":"token","token":"Minus","literal":"-","start":0,"end":1}"#,
+                r#"{"type":"token","token":"Ident","literal":"x","start":1,"end":2}"#,
+                r#"{"type":"token","token":"EOF","literal":"","start":2,"end":2}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_diagnostics() {
+        assert_eq!(
+            run("(1\n", OutputMode::Json),
+            "{\"type\":\"diagnostic\",\"code\":\"P0001\",\"severity\":\"error\",\
+             \"message\":\"Expected next token to be RParen, got EOF instead\",\
+             \"file\":\"<repl>\",\"span\":{\"start\":2,\"end\":2,\"line\":1,\"column\":3},\
+             \"suggestion\":\"insert `)`\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_tokens_command() {
+        assert_eq!(
+            run(":tokens x + 1\n", OutputMode::Text),
+            ">> 0..1 Ident(\"x\")\n2..3 Plus\n4..5 Int(\"1\")\n5..5 EOF\n>> "
+        );
+    }
+
+    #[test]
+    fn test_tokens_view() {
+        let mut output = vec![];
+        let input = "-x\n:env\n";
+        start_with_view(
+            &mut input.as_bytes(),
+            &mut output,
+            OutputMode::Text,
+            View::Tokens,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            ">> 0..1 Minus\n1..2 Ident(\"x\")\n2..2 EOF\n>> >> "
+        );
+    }
+
+    #[test]
+    fn test_ast_command() {
+        let output = run(":ast -a\n", OutputMode::Text);
+        assert!(output.contains("Prefix {"), "{}", output);
+        assert!(output.contains("operator: Minus"), "{}", output);
+        assert!(output.contains("Identifier(\n"), "{}", output);
+    }
+
+    #[test]
+    fn test_env_command() {
+        assert_eq!(
+            run(
+                "let x = 1;\nlet y = x + 1;\nlet x = true;\n:env\n",
+                OutputMode::Text
+            ),
+            ">> let x = 1;\n>> let y = (x + 1);\n>> let x = true;\n>> y = (x + 1)\nx = true\n>> "
+        );
+    }
+
+    #[test]
+    fn test_is_incomplete() {
+        assert!(!is_incomplete("let x = 5;"));
+        assert!(!is_incomplete("if (x) { y }"));
+        assert!(!is_incomplete(""));
+        assert!(is_incomplete("if (x) {"));
+        assert!(is_incomplete("(1 + 2"));
+        assert!(is_incomplete("let x ="));
+        assert!(is_incomplete("1 +\n"));
+        assert!(!is_incomplete("}"));
+        assert!(is_incomplete("[1, 2"));
+        assert!(is_incomplete("xs |>"));
+        assert!(is_incomplete("1.."));
+        assert!(is_incomplete("\"Hello, ${name"));
+        assert!(is_incomplete("\"two\nlines"));
+        assert!(!is_incomplete("\"Hello, ${name}!\""));
+    }
+
+    #[test]
+    fn test_continuation_lines() {
+        assert_eq!(
+            run("if (x) {\n  y\n} else {\n  z }\n", OutputMode::Text),
+            ">> .. .. .. if (x) { y } else { z }\n>> "
+        );
+        assert_eq!(run("1 +\n2\n", OutputMode::Text), ">> .. (1 + 2)\n>> ");
+    }
+
+    #[test]
+    fn test_abandon_continuation() {
+        assert_eq!(run("(1 +\n\n2\n", OutputMode::Text), ">> .. >> 2\n>> ");
+    }
+
+    #[test]
+    fn test_unterminated_input_at_eof() {
+        assert_eq!(
+            run("(1", OutputMode::Text),
+            ">> .. error: Expected next token to be RParen, got EOF instead\n"
+        );
+    }
+
+    #[test]
+    fn test_complete_command() {
+        assert_eq!(
+            run(
+                "let length = 1;\nlet lemma = 2;\n:complete le\n",
+                OutputMode::Text
+            ),
+            ">> let length = 1;\n>> let lemma = 2;\n>> lemma\nlength\nlet\n>> "
+        );
+        assert_eq!(
+            run(":complete f\n:complete zz\n", OutputMode::Json),
+            "{\"type\":\"completions\",\"output\":[\"false\",\"fn\"]}\n\
+             {\"type\":\"completions\",\"output\":[]}\n"
+        );
+    }
+
+    #[test]
+    fn test_help_and_unknown_commands() {
+        assert!(run(":help\n", OutputMode::Text).contains(":tokens <source>"));
+        assert_eq!(
+            run(":nope\n", OutputMode::Text),
+            ">> error: Unknown command :nope; try :help for a list of commands\n>> "
+        );
     }
 }
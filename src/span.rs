@@ -0,0 +1,55 @@
+use std::fmt::{self, Display};
+
+/// A half-open range of byte offsets into the source text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// Length of the span in bytes.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Check if the span covers no text.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The 1-based line and column at which the span starts in `source`,
+    /// counting columns in characters.
+    pub fn location(&self, source: &str) -> (usize, usize) {
+        let before = &source[..self.start.min(source.len())];
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        let line = before.matches('\n').count() + 1;
+        let column = before[line_start..].chars().count() + 1;
+        (line, column)
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_location() {
+        let source = "let x = 1;\nlet é = x;\n";
+        assert_eq!(Span::new(0, 3).location(source), (1, 1));
+        assert_eq!(Span::new(8, 9).location(source), (1, 9));
+        assert_eq!(Span::new(11, 14).location(source), (2, 1));
+        assert_eq!(Span::new(19, 20).location(source), (2, 8));
+        assert_eq!(Span::new(23, 23).location(source), (3, 1));
+    }
+}
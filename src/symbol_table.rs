@@ -0,0 +1,291 @@
+//! Name resolution with nested scopes, as a bytecode compiler needs it to
+//! assign each binding a slot, and as tools like the linter need it to tie
+//! each use of a name to its binding.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolScope {
+    /// Bound at the top level.
+    Global,
+    /// Bound in the current function.
+    Local,
+    /// One of the builtin functions.
+    Builtin,
+    /// Bound in an enclosing function and captured by the current one.
+    Free,
+    /// The name the current function was bound to, so it can call itself.
+    Function,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub scope: SymbolScope,
+    /// The slot of the binding within its scope: a global or local index,
+    /// a builtin's index, or an index into the free symbols.
+    pub index: usize,
+}
+
+#[derive(Debug, Default)]
+struct Scope {
+    store: HashMap<String, Symbol>,
+    num_definitions: usize,
+    /// The symbols captured from enclosing scopes, as resolved there.
+    free_symbols: Vec<Symbol>,
+}
+
+/// The symbols bound in the global scope and each enclosing function scope
+/// down to the current one.
+#[derive(Debug)]
+pub struct SymbolTable {
+    /// The global scope first, innermost last.
+    scopes: Vec<Scope>,
+    builtins: HashMap<String, Symbol>,
+}
+
+impl Default for SymbolTable {
+    fn default() -> Self {
+        SymbolTable::new()
+    }
+}
+
+impl SymbolTable {
+    /// A table with just the global scope.
+    pub fn new() -> Self {
+        SymbolTable {
+            scopes: vec![Scope::default()],
+            builtins: HashMap::new(),
+        }
+    }
+
+    /// The number of scopes enclosing the current one: 0 at the top level.
+    pub fn depth(&self) -> usize {
+        self.scopes.len() - 1
+    }
+
+    /// Enter a new function scope.
+    pub fn push_scope(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    /// Leave the current function scope, returning the symbols it captured
+    /// from the enclosing scopes, in the order of their free indices.
+    pub fn pop_scope(&mut self) -> Vec<Symbol> {
+        assert!(self.depth() > 0, "can't pop the global scope");
+        self.scopes.pop().expect("no open scope").free_symbols
+    }
+
+    /// The number of names bound in the current scope, counting each time a
+    /// name is rebound.
+    pub fn num_definitions(&self) -> usize {
+        self.current().num_definitions
+    }
+
+    /// The symbols the current scope has captured so far.
+    pub fn free_symbols(&self) -> &[Symbol] {
+        &self.current().free_symbols
+    }
+
+    /// Bind `name` in the current scope to a new slot.
+    pub fn define(&mut self, name: &str) -> Symbol {
+        let scope = if self.depth() == 0 {
+            SymbolScope::Global
+        } else {
+            SymbolScope::Local
+        };
+        let current = self.current_mut();
+        let symbol = Symbol {
+            name: name.to_owned(),
+            scope,
+            index: current.num_definitions,
+        };
+        current.num_definitions += 1;
+        current.store.insert(name.to_owned(), symbol.clone());
+        symbol
+    }
+
+    /// Make `name` the builtin with the given index, visible from every
+    /// scope unless shadowed.
+    pub fn define_builtin(&mut self, index: usize, name: &str) -> Symbol {
+        let symbol = Symbol {
+            name: name.to_owned(),
+            scope: SymbolScope::Builtin,
+            index,
+        };
+        self.builtins.insert(name.to_owned(), symbol.clone());
+        symbol
+    }
+
+    /// Bind `name` in the current scope to the function being compiled.
+    pub fn define_function_name(&mut self, name: &str) -> Symbol {
+        let symbol = Symbol {
+            name: name.to_owned(),
+            scope: SymbolScope::Function,
+            index: 0,
+        };
+        self.current_mut()
+            .store
+            .insert(name.to_owned(), symbol.clone());
+        symbol
+    }
+
+    /// Look `name` up from the current scope outwards. A local of an
+    /// enclosing function is captured as a free symbol of each function
+    /// scope in between.
+    pub fn resolve(&mut self, name: &str) -> Option<Symbol> {
+        self.resolve_at(self.depth(), name)
+    }
+
+    fn resolve_at(&mut self, depth: usize, name: &str) -> Option<Symbol> {
+        if let Some(symbol) = self.scopes[depth].store.get(name) {
+            return Some(symbol.clone());
+        }
+        if depth == 0 {
+            return self.builtins.get(name).cloned();
+        }
+        let outer = self.resolve_at(depth - 1, name)?;
+        match outer.scope {
+            SymbolScope::Global | SymbolScope::Builtin => Some(outer),
+            _ => Some(self.define_free(depth, outer)),
+        }
+    }
+
+    fn define_free(&mut self, depth: usize, original: Symbol) -> Symbol {
+        let scope = &mut self.scopes[depth];
+        let symbol = Symbol {
+            name: original.name.clone(),
+            scope: SymbolScope::Free,
+            index: scope.free_symbols.len(),
+        };
+        scope.free_symbols.push(original);
+        scope.store.insert(symbol.name.clone(), symbol.clone());
+        symbol
+    }
+
+    /// Where `symbol`, as resolved from the current scope, was bound: the
+    /// depth of the scope and the symbol there, following free symbols out
+    /// to the binding they capture. `None` for builtins.
+    pub fn definition(&self, symbol: &Symbol) -> Option<(usize, Symbol)> {
+        let mut depth = self.depth();
+        let mut symbol = symbol;
+        loop {
+            match symbol.scope {
+                SymbolScope::Global => return Some((0, symbol.clone())),
+                SymbolScope::Local | SymbolScope::Function => return Some((depth, symbol.clone())),
+                SymbolScope::Builtin => return None,
+                SymbolScope::Free => {
+                    symbol = &self.scopes[depth].free_symbols[symbol.index];
+                    depth -= 1;
+                }
+            }
+        }
+    }
+
+    fn current(&self) -> &Scope {
+        self.scopes.last().expect("no open scope")
+    }
+
+    fn current_mut(&mut self) -> &mut Scope {
+        self.scopes.last_mut().expect("no open scope")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn symbol(name: &str, scope: SymbolScope, index: usize) -> Symbol {
+        Symbol {
+            name: name.to_owned(),
+            scope,
+            index,
+        }
+    }
+
+    #[test]
+    fn test_define() {
+        let mut table = SymbolTable::new();
+        assert_eq!(table.define("a"), symbol("a", SymbolScope::Global, 0));
+        assert_eq!(table.define("b"), symbol("b", SymbolScope::Global, 1));
+        table.push_scope();
+        assert_eq!(table.define("c"), symbol("c", SymbolScope::Local, 0));
+        table.push_scope();
+        assert_eq!(table.define("d"), symbol("d", SymbolScope::Local, 0));
+        assert_eq!(table.depth(), 2);
+        assert_eq!(table.num_definitions(), 1);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut table = SymbolTable::new();
+        table.define_builtin(0, "len");
+        table.define("a");
+        table.push_scope();
+        table.define("b");
+        table.push_scope();
+        table.define("c");
+
+        assert_eq!(
+            table.resolve("a"),
+            Some(symbol("a", SymbolScope::Global, 0))
+        );
+        assert_eq!(
+            table.resolve("len"),
+            Some(symbol("len", SymbolScope::Builtin, 0))
+        );
+        assert_eq!(table.resolve("c"), Some(symbol("c", SymbolScope::Local, 0)));
+        assert_eq!(table.resolve("b"), Some(symbol("b", SymbolScope::Free, 0)));
+        assert_eq!(table.resolve("b"), Some(symbol("b", SymbolScope::Free, 0)));
+        assert_eq!(table.resolve("x"), None);
+        assert_eq!(table.free_symbols(), &[symbol("b", SymbolScope::Local, 0)]);
+
+        assert_eq!(table.pop_scope(), vec![symbol("b", SymbolScope::Local, 0)]);
+        assert_eq!(table.resolve("b"), Some(symbol("b", SymbolScope::Local, 0)));
+    }
+
+    #[test]
+    fn test_free_through_intermediate_scopes() {
+        let mut table = SymbolTable::new();
+        table.push_scope();
+        table.define("a");
+        table.push_scope();
+        table.push_scope();
+        let free = table.resolve("a").unwrap();
+        assert_eq!(free, symbol("a", SymbolScope::Free, 0));
+        assert_eq!(
+            table.definition(&free),
+            Some((1, symbol("a", SymbolScope::Local, 0)))
+        );
+        // Each scope in between captures `a` too, to pass it on.
+        assert_eq!(table.free_symbols(), &[symbol("a", SymbolScope::Free, 0)]);
+        table.pop_scope();
+        assert_eq!(table.free_symbols(), &[symbol("a", SymbolScope::Local, 0)]);
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let mut table = SymbolTable::new();
+        table.define_builtin(0, "len");
+        table.define("a");
+        table.push_scope();
+        table.define("a");
+        table.define("len");
+        table.define_function_name("f");
+        assert_eq!(table.resolve("a"), Some(symbol("a", SymbolScope::Local, 0)));
+        assert_eq!(
+            table.resolve("len"),
+            Some(symbol("len", SymbolScope::Local, 1))
+        );
+        assert_eq!(
+            table.resolve("f"),
+            Some(symbol("f", SymbolScope::Function, 0))
+        );
+        // Rebinding a name takes a new slot.
+        assert_eq!(table.define("a"), symbol("a", SymbolScope::Local, 2));
+        assert_eq!(
+            table.definition(&symbol("len", SymbolScope::Builtin, 0)),
+            None
+        );
+    }
+}
@@ -1,10 +1,123 @@
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Token {
-    //
+use crate::span::Span;
+use std::fmt;
+
+/// A token as read from the source: what sort of token it is, its text, and
+/// where it was read from.
+///
+/// Tokens compare equal if they have the same kind and text, wherever they
+/// were read from. Their debug output leaves the span out too, so that of
+/// the AST doesn't change with the layout of the source.
+#[derive(Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// The name of an identifier, the digits of a number, the text of a
+    /// string or string segment, or the illegal character. Every other kind
+    /// always has the text given by `TokenKind::literal`.
+    pub literal: String,
+    pub span: Span,
+}
+
+/// The kind of every reserved word, as accepted by `Token::lookup_ident`.
+/// Each word is the kind's `TokenKind::literal`.
+pub const KEYWORDS: &[TokenKind] = &[
+    TokenKind::Function,
+    TokenKind::Let,
+    TokenKind::Const,
+    TokenKind::If,
+    TokenKind::Else,
+    TokenKind::Return,
+    TokenKind::True,
+    TokenKind::False,
+    TokenKind::Try,
+    TokenKind::Catch,
+];
+
+impl Token {
+    pub fn new(kind: TokenKind, literal: impl Into<String>) -> Token {
+        Token {
+            kind,
+            literal: literal.into(),
+            span: Span::default(),
+        }
+    }
+    /// The same token, read from `span`.
+    pub fn at(mut self, span: Span) -> Token {
+        self.span = span;
+        self
+    }
+    pub fn make_int(int: &str) -> Token {
+        Token::new(TokenKind::Int, int)
+    }
+    pub fn make_ident(ident: &str) -> Token {
+        Token::new(TokenKind::Ident, ident)
+    }
+    pub fn make_string(string: &str) -> Token {
+        Token::new(TokenKind::String, string)
+    }
+    pub fn lookup_ident(ident: &str) -> Token {
+        match TokenKind::lookup_keyword(ident) {
+            Some(kind) => Token::from(kind),
+            None => Token::make_ident(ident),
+        }
+    }
+    /// The name of the token's kind.
+    pub fn name(&self) -> &'static str {
+        self.kind.name()
+    }
+}
+
+impl From<TokenKind> for Token {
+    /// A token of `kind` with the text every token of that kind has, or no
+    /// text for the kinds whose text varies.
+    fn from(kind: TokenKind) -> Self {
+        Token::new(kind, kind.literal().unwrap_or(""))
+    }
+}
+
+impl PartialEq for Token {
+    fn eq(&self, other: &Token) -> bool {
+        self.kind == other.kind && self.literal == other.literal
+    }
+}
+
+impl Eq for Token {}
+
+impl PartialEq<TokenKind> for Token {
+    fn eq(&self, kind: &TokenKind) -> bool {
+        self.kind == *kind
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            TokenKind::Ident
+            | TokenKind::Int
+            | TokenKind::String
+            | TokenKind::InterpolationStart
+            | TokenKind::InterpolationMid
+            | TokenKind::InterpolationEnd => f
+                .debug_tuple(&format!("{:?}", self.kind))
+                .field(&self.literal)
+                .finish(),
+            kind => write!(f, "{:?}", kind),
+        }
+    }
+}
+
+/// What sort of token a `Token` is, without the text identifiers, literals
+/// and string segments carry. Comparing kinds is how the parser checks for
+/// an expected token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
     Illegal,
     EOF,
-    Ident(String),
-    Int(String),
+    Ident,
+    Int,
+    String,
+    InterpolationStart,
+    InterpolationMid,
+    InterpolationEnd,
     // Operators
     Assign,
     Plus,
@@ -12,12 +125,24 @@ pub enum Token {
     Bang,
     Asterisk,
     Slash,
+    Power,
 
     Eq,
     NotEq,
 
     Lt,
     Gt,
+    ShiftLeft,
+    ShiftRight,
+
+    Ampersand,
+    Bar,
+    Caret,
+    Tilde,
+
+    Pipe,
+    DotDot,
+    DotDotEq,
     // Delimiters
     Comma,
     Semicolon,
@@ -25,69 +150,129 @@ pub enum Token {
     RParen,
     LBrace,
     RBrace,
+    LBracket,
+    RBracket,
+    Colon,
+    Dot,
     // Keywords
     Function,
     Let,
+    Const,
     If,
     Else,
     Return,
     True,
     False,
+    Try,
+    Catch,
 }
 
-impl Token {
-    pub fn make_int(int: &str) -> Token {
-        Token::Int(int.to_owned())
+impl TokenKind {
+    /// The kind of the reserved word `word`, if it is one.
+    pub fn lookup_keyword(word: &str) -> Option<TokenKind> {
+        KEYWORDS
+            .iter()
+            .copied()
+            .find(|kind| kind.literal() == Some(word))
     }
-    pub fn make_ident(ident: &str) -> Token {
-        Token::Ident(ident.to_string())
-    }
-    pub fn lookup_ident(ident: &str) -> Token {
-        match ident {
-            "let" => Token::Let,
-            "fn" => Token::Function,
-            "if" => Token::If,
-            "else" => Token::Else,
-            "return" => Token::Return,
-            "true" => Token::True,
-            "false" => Token::False,
-            _ => Token::Ident(ident.to_string()),
-        }
-    }
-    pub fn literal(&self) -> &str {
+    pub fn name(self) -> &'static str {
         match self {
-            Token::Illegal => "",
-            Token::EOF => "",
-            Token::Ident(literal) => &literal,
-            Token::Int(literal) => &literal,
-            // Operators
-            Token::Assign => "=",
-            Token::Plus => "+",
-            Token::Minus => "-",
-            Token::Bang => "!",
-            Token::Asterisk => "*",
-            Token::Slash => "/",
-
-            Token::Eq => "==",
-            Token::NotEq => "!=",
-
-            Token::Lt => "<",
-            Token::Gt => ">",
-            // Delimiters
-            Token::Comma => ",",
-            Token::Semicolon => ";",
-            Token::LParen => "(",
-            Token::RParen => ")",
-            Token::LBrace => "{",
-            Token::RBrace => "}",
-            // Keywords
-            Token::Function => "fn",
-            Token::Let => "let",
-            Token::If => "if",
-            Token::Else => "else",
-            Token::Return => "return",
-            Token::True => "true",
-            Token::False => "false",
+            TokenKind::Illegal => "Illegal",
+            TokenKind::EOF => "EOF",
+            TokenKind::Ident => "Ident",
+            TokenKind::Int => "Int",
+            TokenKind::String => "String",
+            TokenKind::InterpolationStart => "InterpolationStart",
+            TokenKind::InterpolationMid => "InterpolationMid",
+            TokenKind::InterpolationEnd => "InterpolationEnd",
+            TokenKind::Assign => "Assign",
+            TokenKind::Plus => "Plus",
+            TokenKind::Minus => "Minus",
+            TokenKind::Bang => "Bang",
+            TokenKind::Asterisk => "Asterisk",
+            TokenKind::Slash => "Slash",
+            TokenKind::Power => "Power",
+            TokenKind::Eq => "Eq",
+            TokenKind::NotEq => "NotEq",
+            TokenKind::Lt => "Lt",
+            TokenKind::Gt => "Gt",
+            TokenKind::ShiftLeft => "ShiftLeft",
+            TokenKind::ShiftRight => "ShiftRight",
+            TokenKind::Ampersand => "Ampersand",
+            TokenKind::Bar => "Bar",
+            TokenKind::Caret => "Caret",
+            TokenKind::Tilde => "Tilde",
+            TokenKind::Pipe => "Pipe",
+            TokenKind::DotDot => "DotDot",
+            TokenKind::DotDotEq => "DotDotEq",
+            TokenKind::Comma => "Comma",
+            TokenKind::Semicolon => "Semicolon",
+            TokenKind::LParen => "LParen",
+            TokenKind::RParen => "RParen",
+            TokenKind::LBrace => "LBrace",
+            TokenKind::RBrace => "RBrace",
+            TokenKind::LBracket => "LBracket",
+            TokenKind::RBracket => "RBracket",
+            TokenKind::Colon => "Colon",
+            TokenKind::Dot => "Dot",
+            TokenKind::Function => "Function",
+            TokenKind::Let => "Let",
+            TokenKind::Const => "Const",
+            TokenKind::If => "If",
+            TokenKind::Else => "Else",
+            TokenKind::Return => "Return",
+            TokenKind::True => "True",
+            TokenKind::False => "False",
+            TokenKind::Try => "Try",
+            TokenKind::Catch => "Catch",
         }
     }
+    /// The source text every token of this kind has, or `None` for the
+    /// kinds whose text varies and for `Illegal` and `EOF`.
+    pub fn literal(self) -> Option<&'static str> {
+        let literal = match self {
+            TokenKind::Assign => "=",
+            TokenKind::Plus => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Bang => "!",
+            TokenKind::Asterisk => "*",
+            TokenKind::Slash => "/",
+            TokenKind::Power => "**",
+            TokenKind::Eq => "==",
+            TokenKind::NotEq => "!=",
+            TokenKind::Lt => "<",
+            TokenKind::Gt => ">",
+            TokenKind::ShiftLeft => "<<",
+            TokenKind::ShiftRight => ">>",
+            TokenKind::Ampersand => "&",
+            TokenKind::Bar => "|",
+            TokenKind::Caret => "^",
+            TokenKind::Tilde => "~",
+            TokenKind::Pipe => "|>",
+            TokenKind::DotDot => "..",
+            TokenKind::DotDotEq => "..=",
+            TokenKind::Comma => ",",
+            TokenKind::Semicolon => ";",
+            TokenKind::LParen => "(",
+            TokenKind::RParen => ")",
+            TokenKind::LBrace => "{",
+            TokenKind::RBrace => "}",
+            TokenKind::LBracket => "[",
+            TokenKind::RBracket => "]",
+            TokenKind::Colon => ":",
+            TokenKind::Dot => ".",
+            TokenKind::Function => "fn",
+            TokenKind::Let => "let",
+            TokenKind::Const => "const",
+            TokenKind::If => "if",
+            TokenKind::Else => "else",
+            TokenKind::Return => "return",
+            TokenKind::True => "true",
+            TokenKind::False => "false",
+            TokenKind::Try => "try",
+            TokenKind::Catch => "catch",
+            _ => return None,
+        };
+        Some(literal)
+    }
 }
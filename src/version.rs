@@ -0,0 +1,48 @@
+/// The crate version, as reported by `monkey --version`.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Cargo features enabled in this build.
+pub const FEATURES: &[&str] = &[];
+
+/// Format version information. The verbose form adds one `key: value` line
+/// per build detail, suitable for pasting into bug reports.
+pub fn version_string(verbose: bool) -> String {
+    let mut out = format!("monkey {}", VERSION);
+    if verbose {
+        let features = if FEATURES.is_empty() {
+            "(none)".to_owned()
+        } else {
+            FEATURES.join(", ")
+        };
+        let profile = if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        };
+        out.push_str(&format!("\nfeatures: {}", features));
+        out.push_str(&format!("\nprofile: {}", profile));
+        out.push_str(&format!(
+            "\ntarget: {}-{}",
+            std::env::consts::ARCH,
+            std::env::consts::OS
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_version_string() {
+        assert_eq!(version_string(false), format!("monkey {}", VERSION));
+
+        let verbose = version_string(true);
+        let mut lines = verbose.lines();
+        assert_eq!(lines.next(), Some(version_string(false).as_str()));
+        assert!(verbose.contains("\nfeatures: "));
+        assert!(verbose.contains("\nprofile: "));
+        assert!(verbose.contains("\ntarget: "));
+    }
+}
@@ -0,0 +1,399 @@
+use crate::ast::{BlockStatement, Expression, Identifier, Program, Statement, StringPart};
+
+/// A read-only traversal of the AST.
+///
+/// Every method defaults to walking the node's children, so implementors
+/// override only the nodes they care about. `Expression` and `Statement` are
+/// `#[non_exhaustive]`: a visitor which matches on them should forward any
+/// variant it doesn't recognise to `visit_other_expression` or
+/// `visit_other_statement`, which keep walking into the children of nodes
+/// added after the visitor was written.
+pub trait Visitor: Sized {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement) {
+        walk_statement(self, stmt);
+    }
+
+    fn visit_block_statement(&mut self, block: &BlockStatement) {
+        walk_block_statement(self, block);
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        walk_expression(self, expr);
+    }
+
+    fn visit_identifier(&mut self, _ident: &Identifier) {}
+
+    fn visit_other_statement(&mut self, stmt: &Statement) {
+        walk_statement(self, stmt);
+    }
+
+    fn visit_other_expression(&mut self, expr: &Expression) {
+        walk_expression(self, expr);
+    }
+}
+
+/// A statement or expression, as yielded by [`Walk`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AstNode<'a> {
+    Statement(&'a Statement),
+    Expression(&'a Expression),
+}
+
+impl<'a> AstNode<'a> {
+    pub fn statement(self) -> Option<&'a Statement> {
+        match self {
+            AstNode::Statement(stmt) => Some(stmt),
+            AstNode::Expression(_) => None,
+        }
+    }
+
+    pub fn expression(self) -> Option<&'a Expression> {
+        match self {
+            AstNode::Expression(expr) => Some(expr),
+            AstNode::Statement(_) => None,
+        }
+    }
+}
+
+/// A pre-order iterator over the statements and expressions under a node,
+/// returned by `Program::walk` and `BlockStatement::walk`.
+///
+/// Nodes are visited from an explicit stack, so walking deeply nested trees
+/// can't overflow. The `Nothing` placeholder a bare `return` holds isn't
+/// yielded.
+pub struct Walk<'a> {
+    /// Nodes still to visit, next last.
+    stack: Vec<AstNode<'a>>,
+}
+
+impl<'a> Walk<'a> {
+    pub(crate) fn new(stack: Vec<AstNode<'a>>) -> Self {
+        Walk { stack }
+    }
+
+    fn push_block(&mut self, block: &'a BlockStatement) {
+        self.stack
+            .extend(block.statements.iter().rev().map(AstNode::Statement));
+    }
+
+    fn push_expr(&mut self, expr: &'a Expression) {
+        if *expr != Expression::Nothing {
+            self.stack.push(AstNode::Expression(expr));
+        }
+    }
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = AstNode<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        // Children are pushed last first, so they come off in source order.
+        match node {
+            AstNode::Statement(stmt) => match stmt {
+                Statement::Let { value: expr, .. }
+                | Statement::Destructure { value: expr, .. }
+                | Statement::Return { expr, .. }
+                | Statement::Expression { expr, .. } => self.push_expr(expr),
+            },
+            AstNode::Expression(expr) => match expr {
+                Expression::Identifier(_)
+                | Expression::IntegerLiteral(_)
+                | Expression::StringLiteral(_)
+                | Expression::Boolean(_)
+                | Expression::Nothing => {}
+                Expression::Prefix { right, .. } => self.push_expr(right),
+                Expression::Infix { left, right, .. } => {
+                    self.push_expr(right);
+                    self.push_expr(left);
+                }
+                Expression::If {
+                    condition,
+                    consequence,
+                    alternative,
+                } => {
+                    if let Some(alternative) = alternative {
+                        self.push_block(alternative);
+                    }
+                    self.push_block(consequence);
+                    self.push_expr(condition);
+                }
+                Expression::Try { body, handler, .. } => {
+                    self.push_block(handler);
+                    self.push_block(body);
+                }
+                Expression::Function { body, .. } => self.push_block(body),
+                Expression::Index { left, index } => {
+                    self.push_expr(index);
+                    self.push_expr(left);
+                }
+                Expression::Slice { left, start, end } => {
+                    for bound in end.iter().chain(start) {
+                        self.push_expr(bound);
+                    }
+                    self.push_expr(left);
+                }
+                Expression::ArrayLiteral(elements) => {
+                    for element in elements.iter().rev() {
+                        self.push_expr(element);
+                    }
+                }
+                Expression::Range { start, end, .. } => {
+                    self.push_expr(end);
+                    self.push_expr(start);
+                }
+                Expression::Call {
+                    function,
+                    arguments,
+                } => {
+                    for argument in arguments.iter().rev() {
+                        self.push_expr(argument);
+                    }
+                    self.push_expr(function);
+                }
+                Expression::Interpolated(parts) => {
+                    for part in parts.iter().rev() {
+                        if let StringPart::Expr(expr) = part {
+                            self.push_expr(expr);
+                        }
+                    }
+                }
+            },
+        }
+        Some(node)
+    }
+}
+
+pub fn walk_program<V: Visitor>(visitor: &mut V, program: &Program) {
+    for stmt in &program.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_block_statement<V: Visitor>(visitor: &mut V, block: &BlockStatement) {
+    for stmt in &block.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_statement<V: Visitor>(visitor: &mut V, stmt: &Statement) {
+    match stmt {
+        Statement::Let { name, value, .. } => {
+            visitor.visit_identifier(name);
+            visitor.visit_expression(value);
+        }
+        Statement::Destructure { pattern, value, .. } => {
+            for name in pattern.bindings() {
+                visitor.visit_identifier(name);
+            }
+            visitor.visit_expression(value);
+        }
+        Statement::Return { expr, .. } => visitor.visit_expression(expr),
+        Statement::Expression { expr, .. } => visitor.visit_expression(expr),
+    }
+}
+
+pub fn walk_expression<V: Visitor>(visitor: &mut V, expr: &Expression) {
+    match expr {
+        Expression::Identifier(ident) => visitor.visit_identifier(ident),
+        Expression::IntegerLiteral(_)
+        | Expression::StringLiteral(_)
+        | Expression::Boolean(_)
+        | Expression::Nothing => {}
+        Expression::Prefix { right, .. } => visitor.visit_expression(right),
+        Expression::Infix { left, right, .. } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        }
+        Expression::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            visitor.visit_expression(condition);
+            visitor.visit_block_statement(consequence);
+            if let Some(alternative) = alternative {
+                visitor.visit_block_statement(alternative);
+            }
+        }
+        Expression::Try {
+            body,
+            binding,
+            handler,
+        } => {
+            visitor.visit_block_statement(body);
+            visitor.visit_identifier(binding);
+            visitor.visit_block_statement(handler);
+        }
+        Expression::Function {
+            parameters, body, ..
+        } => {
+            for parameter in parameters {
+                visitor.visit_identifier(parameter);
+            }
+            visitor.visit_block_statement(body);
+        }
+        Expression::Index { left, index } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(index);
+        }
+        Expression::Slice { left, start, end } => {
+            visitor.visit_expression(left);
+            for bound in start.iter().chain(end) {
+                visitor.visit_expression(bound);
+            }
+        }
+        Expression::ArrayLiteral(elements) => {
+            for element in elements {
+                visitor.visit_expression(element);
+            }
+        }
+        Expression::Range { start, end, .. } => {
+            visitor.visit_expression(start);
+            visitor.visit_expression(end);
+        }
+        Expression::Call {
+            function,
+            arguments,
+        } => {
+            visitor.visit_expression(function);
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        }
+        Expression::Interpolated(parts) => {
+            for part in parts {
+                if let StringPart::Expr(expr) = part {
+                    visitor.visit_expression(expr);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn parse(input: &str) -> Program {
+        let mut parser = Parser::from_input(input);
+        let program = parser.parse_program().unwrap();
+        assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+        program
+    }
+
+    #[derive(Default)]
+    struct IdentCollector(Vec<String>);
+
+    impl Visitor for IdentCollector {
+        fn visit_identifier(&mut self, ident: &Identifier) {
+            self.0.push(ident.0.clone());
+        }
+    }
+
+    #[test]
+    fn test_walk_collects_identifiers() {
+        let program = parse("let a = b + -c; if (d) { e } else { f == g }");
+        let mut collector = IdentCollector::default();
+        collector.visit_program(&program);
+        assert_eq!(collector.0, vec!["a", "b", "c", "d", "e", "f", "g"]);
+    }
+
+    /// Only understands infix expressions, forwarding everything else.
+    #[derive(Default)]
+    struct InfixCounter {
+        infix: usize,
+        idents: usize,
+    }
+
+    impl Visitor for InfixCounter {
+        fn visit_expression(&mut self, expr: &Expression) {
+            match expr {
+                Expression::Infix { left, right, .. } => {
+                    self.infix += 1;
+                    self.visit_expression(left);
+                    self.visit_expression(right);
+                }
+                _ => self.visit_other_expression(expr),
+            }
+        }
+
+        fn visit_identifier(&mut self, _ident: &Identifier) {
+            self.idents += 1;
+        }
+    }
+
+    #[test]
+    fn test_walk_iterator() {
+        let program = parse("let a = -b; if (c) { return d + 1; } else { return; f }");
+        let nodes: Vec<String> = program
+            .walk()
+            .map(|node| match node {
+                AstNode::Statement(stmt) => format!("stmt {}", stmt),
+                AstNode::Expression(expr) => format!("expr {}", expr),
+            })
+            .collect();
+        assert_eq!(
+            nodes,
+            vec![
+                "stmt let a = (-b);",
+                "expr (-b)",
+                "expr b",
+                "stmt if (c) { return (d + 1); } else { return; f }",
+                "expr if (c) { return (d + 1); } else { return; f }",
+                "expr c",
+                "stmt return (d + 1);",
+                "expr (d + 1)",
+                "expr d",
+                "expr 1",
+                "stmt return;",
+                "stmt f",
+                "expr f",
+            ]
+        );
+
+        let idents: Vec<String> = program
+            .exprs()
+            .filter_map(|expr| match expr {
+                Expression::Identifier(ident) => Some(ident.0.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(idents, vec!["b", "c", "d", "f"]);
+    }
+
+    #[test]
+    fn test_statement_iterators() {
+        let program = parse("let a = 1; try { a; b } catch (e) { e }");
+        assert_eq!((&program).into_iter().count(), 2);
+        let handler = match program.iter().nth(1) {
+            Some(Statement::Expression {
+                expr: Expression::Try { body, handler, .. },
+                ..
+            }) => {
+                assert_eq!(body.iter().count(), 2);
+                assert_eq!(body.exprs().count(), 2);
+                handler.clone()
+            }
+            other => panic!("expected try, got {:?}", other),
+        };
+        let statements: Vec<Statement> = handler.into_iter().collect();
+        assert_eq!(statements.len(), 1);
+        let owned: Vec<Statement> = program.into_iter().collect();
+        assert_eq!(owned.len(), 2);
+    }
+
+    #[test]
+    fn test_visit_other_keeps_walking() {
+        let program = parse("!(a + b); if (c) { -(d * e) }");
+        let mut counter = InfixCounter::default();
+        counter.visit_program(&program);
+        assert_eq!(counter.infix, 2);
+        assert_eq!(counter.idents, 5);
+    }
+}
@@ -0,0 +1,90 @@
+//! Runs the `monkey` binary the way an installed copy would be used.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Write `source` to a fresh file in the temporary directory.
+fn script(name: &str, source: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("monkey-cli-{}-{}", std::process::id(), name));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+fn monkey(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_monkey"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_executable_script_with_shebang() {
+    // A script made executable with `chmod +x` starts with a shebang line,
+    // which the lexer skips.
+    let path = script(
+        "shebang.monkey",
+        "#!/usr/bin/env monkey\n/// Add two numbers.\nlet add = fn(a, b) { a + b };\nadd(1, 2);\n",
+    );
+    let path = path.to_str().unwrap();
+
+    let output = monkey(&["check", "--emit=sexpr", path]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "(let add (fn (a b) (block (+ a b))))\n(call add 1 2)\n"
+    );
+
+    let output = monkey(&["doc", path]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("## `add(a, b)`"));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_shebang_only_on_first_line() {
+    let path = script("late-shebang.monkey", "let x = 1;\n#!/usr/bin/env monkey\n");
+    let path = path.to_str().unwrap();
+    let output = monkey(&["check", path]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(":2:1: error[L0005]"));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_deny_warnings() {
+    let path = script("warning.monkey", "let x = 1;\nx + 1;\nx\n");
+    let path = path.to_str().unwrap();
+
+    let output = monkey(&["check", path]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains(":2:1: warning[W0001]"));
+
+    let output = monkey(&["check", "--deny-warnings", path]);
+    assert_eq!(output.status.code(), Some(1));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_file_which_is_not_utf8() {
+    // Files are parsed as they are read, so this only shows up part way.
+    let path = script("latin1.monkey", "");
+    fs::write(&path, b"let x = 1;\nlet caf\xe9 = 2;\n").unwrap();
+    let path = path.to_str().unwrap();
+    let output = monkey(&["check", path]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("did not contain valid UTF-8"), "{}", stderr);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_lint_json_has_locations() {
+    let path = script("lint.monkey", "let x = 1;\nlet y = 2;\ny\n");
+    let path = path.to_str().unwrap();
+    let output = monkey(&["--error-format=json", "lint", path]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\"code\":\"unused-binding\""), "{}", stdout);
+    assert!(stdout.contains("\"line\":1,\"column\":5"), "{}", stdout);
+    fs::remove_file(path).unwrap();
+}